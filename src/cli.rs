@@ -0,0 +1,117 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+
+use crate::model::config::{CostGroupBy, PaperDigest};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Slack channel to post to (overrides SLACK_CHANNEL)
+    #[arg(long, global = true)]
+    pub channel: Option<String>,
+
+    /// Group costs by service, sku, location, project or label:<key> (overrides COST_GROUP_BY)
+    #[arg(long, global = true)]
+    pub group_by: Option<CostGroupBy>,
+
+    /// Print the Slack payloads instead of posting them (same as DRY_RUN=1)
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Summarize recently submitted arXiv papers and post them to Slack
+    NotifyPaper {
+        /// Post papers separately, as thread replies or as one combined message (overrides PAPER_DIGEST)
+        #[arg(long)]
+        digest: Option<PaperDigest>,
+        /// First submission date (UTC) to search, for backfills (overrides the ARXIV_* window)
+        #[arg(long, requires = "to")]
+        from: Option<NaiveDate>,
+        /// Last submission date (UTC, inclusive) to search
+        #[arg(long, requires = "from")]
+        to: Option<NaiveDate>,
+        /// Run only the named feed of PAPER_FEEDS_PATH (all feeds are run by default)
+        #[arg(long)]
+        feed: Option<String>,
+    },
+    /// Post the GCP cost report of a day (or of each day in a range) to Slack
+    NotifyDailyCost {
+        /// Target date in YYYY-MM-DD (defaults to the latest complete UTC day)
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        date: Option<NaiveDate>,
+        /// First day of the range to backfill
+        #[arg(long, requires = "to")]
+        from: Option<NaiveDate>,
+        /// Last day (inclusive) of the range to backfill
+        #[arg(long, requires = "from")]
+        to: Option<NaiveDate>,
+        /// Post one combined report for the range instead of one per day
+        #[arg(long, requires = "from")]
+        combined: bool,
+    },
+    /// Post the cost of the last 7 days compared to the previous 7 days
+    NotifyWeeklyCost {
+        /// Last day of the week in YYYY-MM-DD (defaults to the latest complete UTC day)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Post the cost of a month compared to the previous month, with a forecast
+    NotifyMonthlyCost {
+        /// Target month in YYYY-MM (defaults to the month of the latest complete UTC day)
+        #[arg(long, value_parser = parse_month)]
+        month: Option<NaiveDate>,
+    },
+    /// Alert services whose cost increased beyond the configured thresholds
+    NotifyCostAnomaly {
+        /// Target date in YYYY-MM-DD (defaults to the latest complete UTC day)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+}
+
+impl Cli {
+    // サブコマンド未指定の場合は Cloud Run 互換のため環境変数 CMD を見る
+    pub fn command(&self) -> Result<Command> {
+        if let Some(command) = &self.command {
+            return Ok(command.clone());
+        }
+        let cmd = std::env::var("CMD")
+            .map_err(|_| anyhow!("No command given (set CMD or pass a subcommand)"))?;
+        match cmd.as_str() {
+            "notify_paper" | "notify-paper" => Ok(Command::NotifyPaper {
+                digest: None,
+                from: None,
+                to: None,
+                feed: None,
+            }),
+            "notify_daily_cost" | "notify-daily-cost" => Ok(Command::NotifyDailyCost {
+                date: None,
+                from: None,
+                to: None,
+                combined: false,
+            }),
+            "notify_weekly_cost" | "notify-weekly-cost" => {
+                Ok(Command::NotifyWeeklyCost { date: None })
+            }
+            "notify_monthly_cost" | "notify-monthly-cost" => {
+                Ok(Command::NotifyMonthlyCost { month: None })
+            }
+            "notify_cost_anomaly" | "notify-cost-anomaly" => {
+                Ok(Command::NotifyCostAnomaly { date: None })
+            }
+            cmd => Err(anyhow!("Unknown command: {}", cmd)),
+        }
+    }
+}
+
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+        .map_err(|_| format!("expected YYYY-MM, got {}", s))
+}
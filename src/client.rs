@@ -1,4 +1,15 @@
 pub mod arxiv;
+pub mod aws_cur;
+pub mod azure_cost;
+pub mod bigquery;
+pub mod cost_chart;
+pub mod cost_file;
+pub mod cost_query;
+pub mod cost_source;
+pub mod export_table;
+pub mod object_store;
 pub mod openai;
+pub mod paper_checkpoint;
+pub mod seen_paper;
 pub mod slack;
-pub mod bigquery;
+pub mod slack_api;
@@ -1,51 +1,79 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::model::config::Config;
-use crate::model::paper::PaperModel;
-use anyhow::Result;
+use crate::model::paper::{PaperModel, PaperWindow};
+use anyhow::{anyhow, Result};
 use arxiv::ArxivQueryBuilder;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
+
+static PAGE_SIZE: i32 = 300;
+// 期間内の論文がこのページ数に収まらない場合はエラーにする
+static MAX_PAGES: i32 = 10;
+// arXiv API の利用規約で、連続したリクエストは３秒空けることになっている
+static PAGE_INTERVAL: Duration = Duration::from_secs(3);
 
 pub struct ArxivClient {
     config: Arc<Config>,
 }
 
-impl<'a> ArxivClient {
+impl ArxivClient {
     pub fn new(config: Arc<Config>) -> Self {
         Self { config }
     }
 
-    pub async fn search_past_5_to_6_days(&self) -> Result<Vec<PaperModel>> {
-        let max_paper = 2;
-
-        let query = ArxivQueryBuilder::new()
-            .search_query(&self.config.arxiv_query)
-            .start(0)
-            .max_results(300)
-            .sort_by("submittedDate")
-            .build();
-        let arxivs = arxiv::fetch_arxivs(query).await?;
-
-        let now = Utc::now().naive_utc();
-        let date_to = now - Duration::days(5);
-        let date_from = date_to - Duration::days(1);
-
+    // 期間は検索式で絞り込み (分単位のため、境界はここでも確かめる)、新しい順にページを進める
+    pub async fn search(&self, window: &PaperWindow) -> Result<Vec<PaperModel>> {
+        let search_query = format!(
+            "({}) AND submittedDate:[{} TO {}]",
+            self.config.arxiv_search_query()?,
+            window.from.format("%Y%m%d%H%M"),
+            window.to.format("%Y%m%d%H%M")
+        );
         let mut papers = vec![];
-        for arxiv in arxivs {
-            if papers.len() >= max_paper {
-                break;
+        let mut complete = false;
+        for page in 0..MAX_PAGES {
+            if page > 0 {
+                tokio::time::sleep(PAGE_INTERVAL).await;
             }
-            let published = DateTime::parse_from_rfc3339(&arxiv.published)?;
-            if published.naive_utc() < date_from || date_to <= published.naive_utc() {
-                continue;
+            let query = ArxivQueryBuilder::new()
+                .search_query(&search_query)
+                .start(page * PAGE_SIZE)
+                .max_results(PAGE_SIZE)
+                .sort_by("submittedDate")
+                .sort_order("descending")
+                .build();
+            let arxivs = arxiv::fetch_arxivs(query).await?;
+            let count = arxivs.len();
+
+            let mut reached_from = false;
+            for arxiv in arxivs {
+                let published = DateTime::parse_from_rfc3339(&arxiv.published)?.with_timezone(&Utc);
+                if published < window.from {
+                    reached_from = true;
+                    continue;
+                }
+                if window.to <= published {
+                    continue;
+                }
+                papers.push(PaperModel {
+                    url: arxiv.id,
+                    title: arxiv.title.replace("\n", " "),
+                    summary: arxiv.summary.replace("\n", " "),
+                    authors: arxiv.authors,
+                })
+            }
+            if reached_from || count < PAGE_SIZE as usize {
+                complete = true;
+                break;
             }
-            papers.push(PaperModel {
-                url: arxiv.id,
-                published: arxiv.published,
-                title: arxiv.title.replace("\n", " "),
-                summary: arxiv.summary.replace("\n", " "),
-                authors: arxiv.authors,
-            })
+        }
+        if !complete {
+            return Err(anyhow!(
+                "More than {} papers submitted {} - {} (narrow the query or the window)",
+                MAX_PAGES * PAGE_SIZE,
+                window.from,
+                window.to
+            ));
         }
 
         Ok(papers)
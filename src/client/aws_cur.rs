@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::{
+    client::{
+        cost_source::CostSource,
+        export_table::{self, get, get_f64, parse_date, ExportRecord},
+        object_store::{object_format, read_object},
+    },
+    model::{
+        config::{Config, CostGroupBy},
+        currency::Currency,
+        date_range::DateRange,
+        gcp_cost::CostRow,
+    },
+};
+
+static NO_ACCOUNT: &str = "(no account)";
+static NO_GROUP: &str = "(none)";
+// クレジットとして扱う lineItem/LineItemType (それ以外は gross に含める)
+static CREDIT_LINE_ITEM_TYPES: [&str; 7] = [
+    "Credit",
+    "Refund",
+    "BundledDiscount",
+    "Discount",
+    "EdpDiscount",
+    "PrivateRateDiscount",
+    "SavingsPlanNegation",
+];
+
+// AWS Cost and Usage Report (CUR) のファイルを読むコストデータの取得元
+// レガシー CUR の CSV (`lineItem/UnblendedCost`) と、CUR 2.0 / Athena 向けの Parquet (`line_item_unblended_cost`) に対応する
+// プロジェクトは利用アカウント ID で、GCP_COST_PROJECT_IDS による絞り込みはしない
+pub struct AwsCurCostSource {
+    config: Arc<Config>,
+    location: String,
+}
+
+impl AwsCurCostSource {
+    pub fn new(config: Arc<Config>, location: &str) -> Self {
+        Self {
+            config,
+            location: location.to_string(),
+        }
+    }
+
+    async fn read_records(&self) -> Result<Vec<ExportRecord>> {
+        let data = read_object(&self.config.s3, &self.location).await?;
+        let records = match object_format(&self.location) {
+            "csv" => export_table::parse_csv(&data)?,
+            "parquet" => export_table::parse_parquet(data)?,
+            _ => Err(anyhow!(
+                "Unsupported CUR file: {} (expected .csv, .csv.gz or .parquet)",
+                self.location
+            ))?,
+        };
+        // 列名を Parquet と同じ snake_case に揃える
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                record
+                    .into_iter()
+                    .map(|(column, value)| (normalize_column(&column), value))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CostSource for AwsCurCostSource {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>> {
+        let mut rows = vec![];
+        for record in self.read_records().await? {
+            let usage_start = get(&record, &["line_item_usage_start_date"]).unwrap_or_default();
+            let date = parse_date(usage_start).ok_or(anyhow!(
+                "Invalid line_item_usage_start_date: {}",
+                usage_start
+            ))?;
+            if date < range.from || range.to < date {
+                continue;
+            }
+
+            let cost = get_f64(&record, &["line_item_unblended_cost"])?;
+            let is_credit = get(&record, &["line_item_line_item_type"])
+                .is_some_and(|t| CREDIT_LINE_ITEM_TYPES.contains(&t));
+            rows.push(CostRow {
+                project: get(&record, &["line_item_usage_account_id"])
+                    .unwrap_or(NO_ACCOUNT)
+                    .to_string(),
+                group: group(&record, &self.config.cost_group_by)
+                    .unwrap_or_else(|| NO_GROUP.to_string()),
+                gross: if is_credit { 0.0 } else { cost },
+                credits: if is_credit { cost } else { 0.0 },
+                date,
+                currency: Currency::new(
+                    get(&record, &["line_item_currency_code"]).unwrap_or("USD"),
+                ),
+            });
+        }
+        Ok(rows)
+    }
+}
+
+fn group(record: &ExportRecord, group_by: &CostGroupBy) -> Option<String> {
+    let service = get(record, &["product_product_name", "line_item_product_code"]);
+    match group_by {
+        CostGroupBy::Service => service.map(str::to_string),
+        CostGroupBy::Sku => Some(format!(
+            "{} / {}",
+            service.unwrap_or_default(),
+            get(record, &["line_item_usage_type"]).unwrap_or_default()
+        )),
+        CostGroupBy::Label(key) => get(
+            record,
+            &[&normalize_column(&format!("resource_tags_user_{}", key))],
+        )
+        .map(str::to_string),
+        CostGroupBy::Location => get(
+            record,
+            &["product_region_code", "product_region", "product_location"],
+        )
+        .map(str::to_string),
+        CostGroupBy::Project => get(record, &["line_item_usage_account_id"]).map(str::to_string),
+    }
+}
+
+// `lineItem/UnblendedCost` -> `line_item_unblended_cost`, `resourceTags/user:team` -> `resource_tags_user_team`
+fn normalize_column(column: &str) -> String {
+    let mut normalized = String::new();
+    let mut after_lower = false;
+    for c in column.chars() {
+        match c {
+            '/' | ':' | '-' | ' ' | '_' => {
+                if !normalized.is_empty() && !normalized.ends_with('_') {
+                    normalized.push('_');
+                }
+                after_lower = false;
+            }
+            c if c.is_ascii_uppercase() => {
+                if after_lower {
+                    normalized.push('_');
+                }
+                normalized.push(c.to_ascii_lowercase());
+                after_lower = false;
+            }
+            c => {
+                normalized.push(c);
+                after_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+            }
+        }
+    }
+    normalized
+}
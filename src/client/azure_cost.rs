@@ -0,0 +1,122 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::{
+    client::{
+        cost_source::CostSource,
+        export_table::{self, get, get_f64, parse_date, ExportRecord},
+        object_store::{object_format, read_object},
+    },
+    model::{
+        config::{Config, CostGroupBy},
+        currency::Currency,
+        date_range::DateRange,
+        gcp_cost::CostRow,
+    },
+};
+
+static NO_SUBSCRIPTION: &str = "(no subscription)";
+static NO_GROUP: &str = "(none)";
+
+// Azure Cost Management のエクスポート (CSV) を読むコストデータの取得元
+// 実績コスト (CostInBillingCurrency) と旧形式 (PreTaxCost) の列名に対応する
+// プロジェクトはサブスクリプション名で、GCP_COST_PROJECT_IDS による絞り込みはしない
+pub struct AzureCostSource {
+    config: Arc<Config>,
+    location: String,
+}
+
+impl AzureCostSource {
+    pub fn new(config: Arc<Config>, location: &str) -> Self {
+        Self {
+            config,
+            location: location.to_string(),
+        }
+    }
+
+    async fn read_records(&self) -> Result<Vec<ExportRecord>> {
+        let data = read_object(&self.config.s3, &self.location).await?;
+        match object_format(&self.location) {
+            "csv" => export_table::parse_csv(&data),
+            "parquet" => export_table::parse_parquet(data),
+            _ => Err(anyhow!(
+                "Unsupported Azure cost export: {} (expected .csv, .csv.gz or .parquet)",
+                self.location
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl CostSource for AzureCostSource {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>> {
+        let mut rows = vec![];
+        for record in self.read_records().await? {
+            let usage_date =
+                get(&record, &["Date", "UsageDateTime", "UsageDate"]).unwrap_or_default();
+            let date = parse_date(usage_date).ok_or(anyhow!("Invalid Date: {}", usage_date))?;
+            if date < range.from || range.to < date {
+                continue;
+            }
+
+            let cost = get_f64(
+                &record,
+                &["CostInBillingCurrency", "PreTaxCost", "Cost", "CostUSD"],
+            )?;
+            // 返金はマイナスの金額で出力される
+            let is_refund = get(&record, &["ChargeType"]).is_some_and(|t| t == "Refund");
+            rows.push(CostRow {
+                project: get(&record, &["SubscriptionName", "SubscriptionId"])
+                    .unwrap_or(NO_SUBSCRIPTION)
+                    .to_string(),
+                group: group(&record, &self.config.cost_group_by)
+                    .unwrap_or_else(|| NO_GROUP.to_string()),
+                gross: if is_refund { 0.0 } else { cost },
+                credits: if is_refund { cost } else { 0.0 },
+                date,
+                currency: Currency::new(
+                    get(
+                        &record,
+                        &["BillingCurrencyCode", "BillingCurrency", "Currency"],
+                    )
+                    .unwrap_or("USD"),
+                ),
+            });
+        }
+        Ok(rows)
+    }
+}
+
+fn group(record: &ExportRecord, group_by: &CostGroupBy) -> Option<String> {
+    let service = get(record, &["MeterCategory", "ServiceName", "ConsumedService"]);
+    match group_by {
+        CostGroupBy::Service => service.map(str::to_string),
+        CostGroupBy::Sku => Some(format!(
+            "{} / {}",
+            service.unwrap_or_default(),
+            get(record, &["MeterName", "MeterSubCategory"]).unwrap_or_default()
+        )),
+        CostGroupBy::Label(key) => parse_tags(get(record, &["Tags"])?).remove(key),
+        CostGroupBy::Location => get(record, &["ResourceLocation", "Location"]).map(str::to_string),
+        CostGroupBy::Project => {
+            get(record, &["SubscriptionName", "SubscriptionId"]).map(str::to_string)
+        }
+    }
+}
+
+// Tags 列は `"team": "infra","env": "prod"` のように外側の {} が無い JSON で出力されることがある
+fn parse_tags(tags: &str) -> HashMap<String, String> {
+    let tags = tags.trim();
+    let json = if tags.starts_with('{') {
+        tags.to_string()
+    } else {
+        format!("{{{}}}", tags)
+    };
+    serde_json::from_str(&json).unwrap_or_default()
+}
@@ -1,114 +1,140 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc};
 
-use chrono::{Datelike, Duration, NaiveDateTime};
-use google_bigquery2::api::TableRow;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use google_bigquery2::api::{ErrorProto, TableRow};
 
-use crate::model::{
-    config::Config,
-    gcp_cost::{CostReport, ServiceToCostReportMap},
+use crate::{
+    client::{cost_query::CostQuery, cost_source::CostSource},
+    model::{config::Config, currency::Currency, date_range::DateRange, gcp_cost::CostRow},
 };
 use anyhow::{anyhow, Ok, Result};
 
 static DEFAULT_CREDENTIAL_PATH: &str = ".config/gcloud/application_default_credentials.json";
+static NO_PROJECT: &str = "(no project)";
+static NO_GROUP: &str = "(none)";
+// getQueryResults が完了を待つ時間と、その最大回数
+static QUERY_TIMEOUT_MS: u32 = 10_000;
+static MAX_QUERY_POLLS: u32 = 30;
+
+type BigqueryHub =
+    google_bigquery2::Bigquery<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
 
 pub struct BigqueryClient {
     config: Arc<Config>,
 }
 
-impl<'a> BigqueryClient {
+impl BigqueryClient {
     pub fn new(config: Arc<Config>) -> Self {
         Self { config }
     }
 
-    pub async fn get_cost(
+    // jobs.query は時間内に終わらない場合や結果が大きい場合に一部の行しか返さないため、
+    // 完了するまで getQueryResults をポーリングし、全てのページを読む
+    async fn run_query(
         &self,
-        start_of_target_date: NaiveDateTime,
-    ) -> Result<(ServiceToCostReportMap, f64)> {
-        let yesterday_from_target_date = start_of_target_date - Duration::days(1);
-        let start_of_month = start_of_target_date
-            .with_day(1)
-            .ok_or(anyhow!("Failed to get the start_of_month"))?;
+        hub: &BigqueryHub,
+        req: google_bigquery2::api::QueryRequest,
+    ) -> Result<Vec<TableRow>> {
+        let (_, res) = hub
+            .jobs()
+            .query(req, &self.config.gcp_project_id)
+            .doit()
+            .await?;
+        check_errors(res.errors.as_ref())?;
+
+        let mut rows = res.rows.unwrap_or_default();
+        let mut page_token = res.page_token;
+        if res.job_complete.unwrap_or(false) && page_token.is_none() {
+            return Ok(rows);
+        }
 
-        let start_of_next_month = start_of_month
-            .with_month(start_of_month.month() + 1)
-            .ok_or(anyhow!("Invalid month value"))?;
+        let job = res
+            .job_reference
+            .ok_or(anyhow!("Missing job reference in the query response"))?;
+        let job_id = job.job_id.ok_or(anyhow!("Missing job id"))?;
+        let mut polls = 0;
+        loop {
+            let mut call = hub
+                .jobs()
+                .get_query_results(&self.config.gcp_project_id, &job_id)
+                .timeout_ms(QUERY_TIMEOUT_MS);
+            if let Some(location) = &job.location {
+                call = call.location(location);
+            }
+            if let Some(token) = &page_token {
+                call = call.page_token(token);
+            }
+            let (_, page) = call.doit().await?;
+            check_errors(page.errors.as_ref())?;
+
+            if !page.job_complete.unwrap_or(false) {
+                polls += 1;
+                if polls >= MAX_QUERY_POLLS {
+                    Err(anyhow!("Query job {} did not complete in time", job_id))?;
+                }
+                continue;
+            }
+            rows.extend(page.rows.unwrap_or_default());
+            page_token = page.page_token;
+            if page_token.is_none() {
+                return Ok(rows);
+            }
+        }
+    }
+}
 
+#[async_trait]
+impl CostSource for BigqueryClient {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>> {
+        if self.config.gcp_project_id.is_empty() {
+            Err(anyhow!(
+                "GCP_PROJECT_ID is required for the BigQuery cost source"
+            ))?;
+        }
         let auth = get_auth(self.config.gcp_credential_path.clone()).await?;
 
-        let query = format!(
-            "
-SELECT service.description, cost, datetime(_PARTITIONTIME)  FROM `{}` 
-WHERE TIMESTAMP(\"{}\") <= TIMESTAMP_TRUNC(_PARTITIONTIME, DAY)
-AND TIMESTAMP_TRUNC(_PARTITIONTIME, DAY) < TIMESTAMP(\"{}\")
-AND project.id = \"{}\"",
-            self.config.gcp_bigquery_cost_table,
-            start_of_month.format("%Y-%m-%d").to_string(),
-            start_of_next_month.format("%Y-%m-%d").to_string(),
-            self.config.gcp_project_id,
-        );
-        // println!("{}", query);
+        let req = CostQuery::new(&self.config.gcp_bigquery_cost_table, *range)?
+            .projects(&self.config.cost_projects)
+            .group_by(&self.config.cost_group_by)
+            .to_request();
+        // println!("{}", req.query.as_deref().unwrap_or_default());
 
         let https = hyper_tls::HttpsConnector::new();
         let client = hyper::Client::builder().build::<_, hyper::Body>(https);
 
         let hub = google_bigquery2::Bigquery::new(client, auth);
-        let mut req = google_bigquery2::api::QueryRequest::default();
-        req.query = Some(query.to_string());
-        req.use_legacy_sql = Some(false);
 
-        let result = hub
-            .jobs()
-            .query(req, &self.config.gcp_project_id)
-            .doit()
-            .await?;
-        let query_res: google_bigquery2::api::QueryResponse = result.1;
-
-        let mut date_to_service_cost: HashMap<String, HashMap<String, f64>> = HashMap::new();
-        let mut month_total = 0.0;
-        for row in query_res.rows.ok_or(anyhow!("Failed to get row"))? {
-            let service: String = extract_value(&row, 0)?;
-            // println!("{}", service);
-            let cost: f64 = extract_value(&row, 1)?;
-            // println!("{}", cost);
-            let date: String = extract_value(&row, 2)?;
-            // println!("{}", date);
-
-            let service_map = date_to_service_cost
-                .entry(date)
-                .or_insert_with(HashMap::new);
-            let current_cost = service_map.entry(service).or_insert(0.0);
-            *current_cost += cost;
-            month_total += cost;
-        }
-        // println!("{:?}", date_to_service_cost);
-
-        let mut service_to_cost_report: ServiceToCostReportMap = ServiceToCostReportMap::new();
-        for (service, cost) in date_to_service_cost
-            .get(&(start_of_target_date.format("%Y-%m-%d").to_string() + "T00:00:00"))
-            .ok_or(anyhow!("Failed to get service_to_cost"))?
-        {
-            let cost_2day_ago = date_to_service_cost
-                .get(&(yesterday_from_target_date.format("%Y-%m-%d").to_string() + "T00:00:00"))
-                .and_then(|server_to_cost| server_to_cost.get(service));
-
-            let diff_cost = cost - cost_2day_ago.unwrap_or(&0.0);
-            let diff_rate = match cost_2day_ago {
-                Some(&cost_2day_ago) if cost_2day_ago != 0.0 => Some(cost / cost_2day_ago),
-                _ => None,
-            };
-
-            service_to_cost_report.insert(
-                service.to_string(),
-                CostReport {
-                    diff_rate,
-                    diff_cost,
-                    cost: cost.to_owned(),
-                },
-            );
-        }
-        // println!("{:?}", service_to_cost_report);
+        self.run_query(&hub, req)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(CostRow {
+                    // 請求アカウント単位の費用 (サポート料金など) はプロジェクトを持たない
+                    project: extract_value(row, 0).unwrap_or_else(|_| NO_PROJECT.to_string()),
+                    // ラベルが付いていない場合などは NULL になる
+                    group: extract_value(row, 1).unwrap_or_else(|_| NO_GROUP.to_string()),
+                    gross: extract_value(row, 2)?,
+                    credits: extract_value(row, 3)?,
+                    date: extract_value::<NaiveDateTime>(row, 4)?.date(),
+                    currency: Currency::new(&extract_value::<String>(row, 5)?),
+                })
+            })
+            .collect()
+    }
+}
 
-        Ok((service_to_cost_report, month_total))
+fn check_errors(errors: Option<&Vec<ErrorProto>>) -> Result<()> {
+    match errors.and_then(|errors| errors.first()) {
+        Some(error) => Err(anyhow!(
+            "Query failed: {}",
+            error.message.as_deref().unwrap_or("unknown error")
+        )),
+        None => Ok(()),
     }
 }
 
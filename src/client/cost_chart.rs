@@ -0,0 +1,195 @@
+use std::{collections::HashMap, fs, sync::OnceLock};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use plotters::{
+    prelude::*,
+    style::{register_font, FontStyle},
+};
+
+use crate::model::{date_range::DateRange, gcp_cost::DailyCostReport};
+
+static WIDTH: u32 = 1200;
+static HEIGHT: u32 = 600;
+// 色を分けるサービスの数 (それより多い場合は残りを Other にまとめる)
+static MAX_SERVICES: usize = 8;
+static TREND_DAYS: usize = 7;
+static OTHER: &str = "Other";
+static PALETTE: [RGBColor; MAX_SERVICES] = [
+    RGBColor(0x35, 0x60, 0xa6),
+    RGBColor(0x2e, 0xb8, 0x86),
+    RGBColor(0xf2, 0xc7, 0x44),
+    RGBColor(0xd6, 0x2d, 0x20),
+    RGBColor(0x8e, 0x5e, 0xa2),
+    RGBColor(0x1d, 0xa1, 0xc9),
+    RGBColor(0xe6, 0x7e, 0x22),
+    RGBColor(0x9e, 0x9e, 0x9e),
+];
+
+// plotters はフォントを 'static で持つため、プロセス内で一度だけ読み込む
+static FONT: OnceLock<&'static [u8]> = OnceLock::new();
+
+// 対象日の月の日別コストをサービス毎に積み上げた棒グラフと、直近７日間の平均の折れ線の PNG
+pub fn render_cost_chart(
+    title: &str,
+    report: &DailyCostReport,
+    target_date: NaiveDate,
+    font_path: &str,
+) -> Result<Vec<u8>> {
+    load_font(font_path)?;
+
+    let month = DateRange::month_of(target_date)?;
+    let days: Vec<NaiveDate> = DateRange::new(month.from, target_date)?.days().collect();
+    let services = chart_services(report, &days);
+    let daily_totals: Vec<f64> = days
+        .iter()
+        .map(|day| {
+            report
+                .date_to_service_cost
+                .get(day)
+                .map(|costs| costs.values().sum())
+                .unwrap_or(0.0)
+        })
+        .collect();
+    let max_cost = daily_totals.iter().copied().fold(0.0, f64::max);
+    let currency = &report.currency;
+
+    let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, ("sans-serif", 24))
+            .margin(16)
+            .x_label_area_size(32)
+            .y_label_area_size(96)
+            .build_cartesian_2d(
+                0.5..month.num_days() as f64 + 0.5,
+                0.0..if max_cost > 0.0 { max_cost * 1.15 } else { 1.0 },
+            )?;
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(month.num_days() as usize)
+            .x_label_formatter(&|x| format!("{}", x.round()))
+            .y_label_formatter(&|y| currency.format(*y))
+            .label_style(("sans-serif", 14))
+            .draw()?;
+
+        // 下から順に積み上げる
+        let mut bases = vec![0.0; days.len()];
+        for (i, service) in services.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            let bars: Vec<_> = days
+                .iter()
+                .enumerate()
+                .map(|(j, day)| {
+                    let cost = service_cost(report, day, service, &services);
+                    let x = day.day() as f64;
+                    let bar = Rectangle::new(
+                        [(x - 0.35, bases[j]), (x + 0.35, bases[j] + cost)],
+                        color.filled(),
+                    );
+                    bases[j] += cost;
+                    bar
+                })
+                .collect();
+            chart
+                .draw_series(bars)?
+                .label(service.as_str())
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled())
+                });
+        }
+
+        let trend = (0..days.len()).map(|i| {
+            let window = &daily_totals[(i + 1).saturating_sub(TREND_DAYS)..=i];
+            (
+                days[i].day() as f64,
+                window.iter().sum::<f64>() / window.len() as f64,
+            )
+        });
+        chart
+            .draw_series(LineSeries::new(trend, BLACK.stroke_width(2)))?
+            .label(format!("{}-day average", TREND_DAYS))
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 10, y)], BLACK.stroke_width(2)));
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font(("sans-serif", 14))
+            .draw()?;
+        root.present()?;
+    }
+
+    encode_png(&buffer)
+}
+
+fn load_font(path: &str) -> Result<()> {
+    if FONT.get().is_some() {
+        return Ok(());
+    }
+    let data =
+        fs::read(path).map_err(|e| anyhow!("Failed to read COST_CHART_FONT {}: {}", path, e))?;
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+    register_font("sans-serif", FontStyle::Normal, data)
+        .map_err(|_| anyhow!("Invalid COST_CHART_FONT: {}", path))?;
+    let _ = FONT.set(data);
+    Ok(())
+}
+
+// 期間内の合計が大きい順 (MAX_SERVICES を超える分は Other)
+fn chart_services(report: &DailyCostReport, days: &[NaiveDate]) -> Vec<String> {
+    let mut service_to_total: HashMap<&str, f64> = HashMap::new();
+    for day in days {
+        for (service, cost) in report.date_to_service_cost.get(day).into_iter().flatten() {
+            *service_to_total.entry(service).or_insert(0.0) += cost;
+        }
+    }
+    let mut services: Vec<_> = service_to_total.into_iter().collect();
+    services.sort_by(|(a, a_total), (b, b_total)| b_total.total_cmp(a_total).then(a.cmp(b)));
+
+    let mut services: Vec<String> = services
+        .into_iter()
+        .map(|(service, _)| service.to_string())
+        .collect();
+    if services.len() > MAX_SERVICES {
+        services.truncate(MAX_SERVICES - 1);
+        services.push(OTHER.to_string());
+    }
+    services
+}
+
+fn service_cost(
+    report: &DailyCostReport,
+    day: &NaiveDate,
+    service: &str,
+    services: &[String],
+) -> f64 {
+    let costs = report.date_to_service_cost.get(day).into_iter().flatten();
+    if service == OTHER && services.len() == MAX_SERVICES {
+        costs
+            .filter(|(name, _)| !services[..MAX_SERVICES - 1].contains(name))
+            .map(|(_, cost)| cost)
+            .sum()
+    } else {
+        costs
+            .filter(|(name, _)| name.as_str() == service)
+            .map(|(_, cost)| cost)
+            .sum()
+    }
+}
+
+fn encode_png(rgb: &[u8]) -> Result<Vec<u8>> {
+    let mut png = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(rgb)?;
+    }
+    Ok(png)
+}
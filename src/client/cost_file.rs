@@ -0,0 +1,458 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    client::{
+        cost_source::CostSource,
+        export_table::{self, get, get_f64, parse_date},
+        object_store::{object_format, read_object},
+    },
+    model::{
+        config::{Config, CostGroupBy, CostProjects},
+        currency::Currency,
+        date_range::DateRange,
+        gcp_cost::CostRow,
+    },
+};
+
+static NO_PROJECT: &str = "(no project)";
+static NO_GROUP: &str = "(none)";
+// currency 列の無いエクスポート (AWS / Azure の取得元と同じ)
+static DEFAULT_CURRENCY: &str = "USD";
+
+// 請求エクスポートと同じ形の CSV / JSON ファイルを読むコストデータの取得元
+// GCP の認証情報が無い環境での開発や、他のツールが出力したエクスポートの取り込みに使う
+pub struct FileCostSource {
+    config: Arc<Config>,
+    location: String,
+}
+
+impl FileCostSource {
+    // location はローカルのパス、`s3://bucket/key` または署名付きの `https://` URL
+    pub fn new(config: Arc<Config>, location: &str) -> Self {
+        Self {
+            config,
+            location: location.to_string(),
+        }
+    }
+
+    async fn read_records(&self) -> Result<Vec<BillingRecord>> {
+        let data = read_object(&self.config.s3, &self.location).await?;
+        match object_format(&self.location) {
+            "csv" => parse_csv(&data),
+            "json" | "jsonl" | "ndjson" => parse_json(&data),
+            _ => Err(anyhow!(
+                "Unsupported cost file: {} (expected .csv or .json)",
+                self.location
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl CostSource for FileCostSource {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>> {
+        let mut rows = vec![];
+        for record in self.read_records().await? {
+            let date = record.date()?;
+            if date < range.from || range.to < date {
+                continue;
+            }
+            if let CostProjects::List(projects) = &self.config.cost_projects {
+                if !record
+                    .project
+                    .as_ref()
+                    .is_some_and(|p| projects.contains(p))
+                {
+                    continue;
+                }
+            }
+            rows.push(CostRow {
+                group: record
+                    .group(&self.config.cost_group_by)
+                    .unwrap_or_else(|| NO_GROUP.to_string()),
+                project: record
+                    .project
+                    .clone()
+                    .unwrap_or_else(|| NO_PROJECT.to_string()),
+                gross: record.cost,
+                credits: record.credits,
+                date,
+                currency: Currency::new(&record.currency),
+            });
+        }
+        Ok(rows)
+    }
+}
+
+// エクスポートの１行のうち、集計に使う列
+#[derive(Debug, Default)]
+struct BillingRecord {
+    project: Option<String>,
+    service: Option<String>,
+    sku: Option<String>,
+    location: Option<String>,
+    region: Option<String>,
+    labels: HashMap<String, String>,
+    cost: f64,
+    credits: f64,
+    currency: String,
+    // `2023-08-01 00:00:00 UTC` や `2023-08-01T00:00:00Z` など (UTC)
+    usage_start_time: String,
+}
+
+impl BillingRecord {
+    // BigQuery のパーティション日と同じく UTC の日付
+    fn date(&self) -> Result<NaiveDate> {
+        parse_date(&self.usage_start_time).ok_or(anyhow!(
+            "Invalid usage_start_time: {}",
+            self.usage_start_time
+        ))
+    }
+
+    fn group(&self, group_by: &CostGroupBy) -> Option<String> {
+        match group_by {
+            CostGroupBy::Service => self.service.clone(),
+            CostGroupBy::Sku => Some(format!(
+                "{} / {}",
+                self.service.as_deref().unwrap_or_default(),
+                self.sku.as_deref().unwrap_or_default()
+            )),
+            CostGroupBy::Label(key) => self.labels.get(key).cloned(),
+            CostGroupBy::Location => self.region.clone().or_else(|| self.location.clone()),
+            CostGroupBy::Project => self.project.clone(),
+        }
+    }
+}
+
+// `bq extract --destination_format NEWLINE_DELIMITED_JSON` の出力、またはその配列
+#[derive(Debug, Deserialize)]
+struct JsonBillingRecord {
+    project: Option<JsonId>,
+    service: Option<JsonDescription>,
+    sku: Option<JsonDescription>,
+    location: Option<JsonLocation>,
+    #[serde(default)]
+    labels: Vec<JsonLabel>,
+    cost: f64,
+    #[serde(default)]
+    credits: Vec<JsonCredit>,
+    currency: Option<String>,
+    usage_start_time: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonId {
+    id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonDescription {
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonLocation {
+    location: Option<String>,
+    region: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonLabel {
+    key: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonCredit {
+    amount: f64,
+}
+
+impl From<JsonBillingRecord> for BillingRecord {
+    fn from(record: JsonBillingRecord) -> Self {
+        let (location, region) = record
+            .location
+            .map(|l| (l.location, l.region))
+            .unwrap_or_default();
+        Self {
+            project: record.project.and_then(|p| p.id),
+            service: record.service.and_then(|s| s.description),
+            sku: record.sku.and_then(|s| s.description),
+            location,
+            region,
+            labels: record
+                .labels
+                .into_iter()
+                .map(|label| (label.key, label.value))
+                .collect(),
+            cost: record.cost,
+            credits: record.credits.iter().map(|c| c.amount).sum(),
+            currency: record
+                .currency
+                .unwrap_or_else(|| DEFAULT_CURRENCY.to_string()),
+            usage_start_time: record.usage_start_time,
+        }
+    }
+}
+
+fn parse_json(data: &[u8]) -> Result<Vec<BillingRecord>> {
+    let content = std::str::from_utf8(data)?;
+    let records: Vec<JsonBillingRecord> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content)?
+    } else {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(records.into_iter().map(BillingRecord::from).collect())
+}
+
+// ヘッダーはエクスポートの列名を `.` でつなげたもの (`project.id`, `service.description` など)
+// credits はクレジットの合計額、ラベルは `labels.<key>` の列で指定する
+fn parse_csv(data: &[u8]) -> Result<Vec<BillingRecord>> {
+    export_table::parse_csv(data)?
+        .into_iter()
+        .map(|record| {
+            Ok(BillingRecord {
+                project: get(&record, &["project.id"]).map(str::to_string),
+                service: get(&record, &["service.description"]).map(str::to_string),
+                sku: get(&record, &["sku.description"]).map(str::to_string),
+                location: get(&record, &["location.location"]).map(str::to_string),
+                region: get(&record, &["location.region"]).map(str::to_string),
+                labels: record
+                    .iter()
+                    .filter_map(|(column, value)| {
+                        column
+                            .strip_prefix("labels.")
+                            .map(|key| (key.to_string(), value.clone()))
+                    })
+                    .collect(),
+                cost: get_f64(&record, &["cost"])?,
+                credits: get_f64(&record, &["credits"])?,
+                currency: get(&record, &["currency"])
+                    .unwrap_or(DEFAULT_CURRENCY)
+                    .to_string(),
+                usage_start_time: get(&record, &["usage_start_time"])
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::model::{
+        config::{
+            ArxivWindowConfig, CostAlertConfig, CostBasis, ForecastMethod, PaperDigest,
+            PaperRankingConfig, S3Config,
+        },
+        openai::Engine,
+    };
+
+    use super::*;
+
+    static CSV: &str = "\
+project.id,service.description,sku.description,labels.team,cost,credits,currency,usage_start_time
+p1,Compute Engine,N2 Core,ml,10.5,-0.5,USD,2023-07-31 00:00:00 UTC
+p1,Compute Engine,N2 Core,ml,12,-1,USD,2023-08-01 00:00:00 UTC
+p1,BigQuery,Analysis,,3,0,USD,2023-08-01 10:00:00 UTC
+p2,Compute Engine,N2 Core,infra,100,0,USD,2023-08-01 00:00:00 UTC
+p1,Cloud Storage,Standard,ml,1.25,0,,2023-08-01T00:00:00Z
+p1,Compute Engine,N2 Core,ml,99,0,USD,2023-08-02 00:00:00 UTC
+";
+
+    static JSON: &str = r#"
+{"project":{"id":"p1"},"service":{"description":"BigQuery"},"labels":[{"key":"team","value":"ml"}],"cost":2.0,"credits":[{"amount":-0.5}],"currency":"JPY","usage_start_time":"2023-08-01 00:00:00 UTC"}
+{"project":{"id":"p1"},"service":{"description":"BigQuery"},"cost":1.0,"usage_start_time":"2023-08-01 01:00:00 UTC"}
+"#;
+
+    // 環境変数や .env に左右されないように、テストでは Config を直接組み立てる
+    fn config(projects: CostProjects, group_by: CostGroupBy) -> Arc<Config> {
+        Arc::new(Config {
+            arxiv_query: String::new(),
+            arxiv_search: None,
+            arxiv_categories: vec![],
+            arxiv_window: ArxivWindowConfig {
+                offset_days: 5,
+                width_days: 1,
+                since_last_run: false,
+                checkpoint_path: String::new(),
+            },
+            openai_api_key: String::new(),
+            slack_bot_token: String::new(),
+            slack_channel: String::new(),
+            platform: String::new(),
+            gcp_credential_path: None,
+            gcp_project_id: String::new(),
+            gcp_bigquery_cost_table: String::new(),
+            cost_sources: vec![],
+            s3: S3Config {
+                endpoint: None,
+                region: String::new(),
+                access_key_id: None,
+                secret_access_key: None,
+                session_token: None,
+            },
+            seen_paper_path: String::new(),
+            paper_digest: PaperDigest::Separate,
+            paper_count: 2,
+            paper_ranking: PaperRankingConfig {
+                keywords: vec![],
+                authors: vec![],
+                llm: false,
+            },
+            paper_language: String::new(),
+            paper_engine: Engine::Gpt4,
+            paper_feeds_path: None,
+            dry_run: true,
+            cost_alert: CostAlertConfig {
+                increase_percent: None,
+                increase_amount: None,
+                z_score: None,
+                z_score_days: 7,
+                mention: None,
+            },
+            cost_projects: projects,
+            cost_group_by: group_by,
+            cost_basis: CostBasis::Net,
+            cost_display_currency: None,
+            cost_exchange_rates: HashMap::new(),
+            cost_project_channels: HashMap::new(),
+            cost_monthly_budgets: HashMap::new(),
+            cost_forecast_method: ForecastMethod::Linear,
+            cost_chart: false,
+            cost_chart_font: String::new(),
+        })
+    }
+
+    // テストが失敗しても一時ファイルを消す
+    struct Sample(PathBuf);
+
+    impl Sample {
+        fn write(name: &str, content: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("cost_file_{}_{}", std::process::id(), name));
+            std::fs::write(&path, content).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for Sample {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 8, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn csv_rows_are_filtered_by_date_and_project() {
+        let sample = Sample::write("rows.csv", CSV);
+        let source = FileCostSource::new(
+            config(
+                CostProjects::List(vec!["p1".to_string()]),
+                CostGroupBy::Service,
+            ),
+            sample.path(),
+        );
+        let range = DateRange::new(date(1), date(1)).unwrap();
+        let mut rows: Vec<_> = source
+            .get_cost_rows(&range)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.project, row.group, row.gross, row.credits, row.currency))
+            .collect();
+        rows.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "p1".to_string(),
+                    "BigQuery".to_string(),
+                    3.0,
+                    0.0,
+                    Currency::new("USD")
+                ),
+                (
+                    "p1".to_string(),
+                    "Cloud Storage".to_string(),
+                    1.25,
+                    0.0,
+                    // currency の無い行は USD
+                    Currency::new("USD")
+                ),
+                (
+                    "p1".to_string(),
+                    "Compute Engine".to_string(),
+                    12.0,
+                    -1.0,
+                    Currency::new("USD")
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn daily_cost_from_csv() {
+        let sample = Sample::write("daily.csv", CSV);
+        let source = FileCostSource::new(
+            config(CostProjects::All, CostGroupBy::Label("team".to_string())),
+            sample.path(),
+        );
+        let project_to_report = source.get_project_cost(date(1)).await.unwrap();
+
+        let report = &project_to_report["p1"];
+        assert_eq!(report.currency, Currency::new("USD"));
+        let ml = &report.service_to_cost["ml"];
+        // 12 - 1 + 1.25 と、前日 (前月末) の 10.5 - 0.5 の比較
+        assert_eq!(ml.cost, 12.25);
+        assert_eq!(ml.diff_cost, 2.25);
+        assert_eq!(ml.diff_rate, Some(12.25 / 10.0));
+        assert_eq!(ml.gross_cost, 13.25);
+        assert_eq!(ml.credits, -1.0);
+        assert_eq!(report.service_to_cost[NO_GROUP].cost, 3.0);
+        let infra = &project_to_report["p2"].service_to_cost["infra"];
+        assert_eq!(infra.cost, 100.0);
+        assert_eq!(infra.diff_rate, None);
+        // 前日比のために読んだ前月末は、月の日別コストに含まない
+        assert_eq!(report.date_to_service_cost.keys().next(), Some(&date(1)));
+    }
+
+    #[tokio::test]
+    async fn json_lines_with_default_currency() {
+        let sample = Sample::write("rows.json", JSON);
+        let source = FileCostSource::new(
+            config(CostProjects::All, CostGroupBy::Label("team".to_string())),
+            sample.path(),
+        );
+        let range = DateRange::new(date(1), date(1)).unwrap();
+        let rows = source.get_cost_rows(&range).await.unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].group, "ml");
+        assert_eq!(rows[0].credits, -0.5);
+        assert_eq!(rows[0].currency, Currency::new("JPY"));
+        assert_eq!(rows[1].group, NO_GROUP);
+        assert_eq!(rows[1].currency, Currency::new("USD"));
+    }
+}
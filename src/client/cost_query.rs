@@ -0,0 +1,336 @@
+use anyhow::{anyhow, Result};
+use google_bigquery2::api::{
+    QueryParameter, QueryParameterType, QueryParameterValue, QueryRequest,
+};
+
+use crate::model::{
+    config::{CostGroupBy, CostProjects},
+    date_range::DateRange,
+};
+
+// 請求エクスポートのテーブルから、プロジェクト別・グループ別・日別の金額を取得するクエリ
+// 日付・プロジェクト・ラベルのキーはクエリパラメータで渡し、テーブル名は検証してから埋め込む
+pub struct CostQuery<'a> {
+    table: &'a str,
+    range: DateRange,
+    projects: &'a CostProjects,
+    group_by: &'a CostGroupBy,
+}
+
+impl<'a> CostQuery<'a> {
+    pub fn new(table: &'a str, range: DateRange) -> Result<Self> {
+        validate_table_id(table)?;
+        Ok(Self {
+            table,
+            range,
+            projects: &CostProjects::All,
+            group_by: &CostGroupBy::Service,
+        })
+    }
+
+    pub fn projects(mut self, projects: &'a CostProjects) -> Self {
+        self.projects = projects;
+        self
+    }
+
+    pub fn group_by(mut self, group_by: &'a CostGroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    // 列の順番は project, group, cost, credits, partition time, currency
+    pub fn sql(&self) -> String {
+        let mut sql = format!(
+            "
+SELECT project.id, {}, cost, IFNULL((SELECT SUM(c.amount) FROM UNNEST(credits) c), 0), datetime(_PARTITIONTIME), currency  FROM `{}`
+WHERE TIMESTAMP(@from_date) <= TIMESTAMP_TRUNC(_PARTITIONTIME, DAY)
+AND TIMESTAMP_TRUNC(_PARTITIONTIME, DAY) < TIMESTAMP(@to_date_exclusive)",
+            self.group_column(),
+            self.table,
+        );
+        if let CostProjects::List(_) = self.projects {
+            sql.push_str("\nAND project.id IN UNNEST(@project_ids)");
+        }
+        sql
+    }
+
+    pub fn parameters(&self) -> Vec<QueryParameter> {
+        let mut parameters = vec![
+            scalar_parameter(
+                "from_date",
+                "DATE",
+                self.range.from.format("%Y-%m-%d").to_string(),
+            ),
+            scalar_parameter(
+                "to_date_exclusive",
+                "DATE",
+                self.range.end_exclusive().format("%Y-%m-%d").to_string(),
+            ),
+        ];
+        if let CostProjects::List(projects) = self.projects {
+            parameters.push(QueryParameter {
+                name: Some("project_ids".to_string()),
+                parameter_type: Some(QueryParameterType {
+                    type_: Some("ARRAY".to_string()),
+                    array_type: Some(Some(Box::new(parameter_type("STRING")))),
+                    ..Default::default()
+                }),
+                parameter_value: Some(QueryParameterValue {
+                    array_values: Some(
+                        projects
+                            .iter()
+                            .map(|project| QueryParameterValue {
+                                value: Some(project.clone()),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    ),
+                    ..Default::default()
+                }),
+            });
+        }
+        if let CostGroupBy::Label(key) = self.group_by {
+            parameters.push(scalar_parameter("label_key", "STRING", key.clone()));
+        }
+        parameters
+    }
+
+    pub fn to_request(&self) -> QueryRequest {
+        QueryRequest {
+            query: Some(self.sql()),
+            use_legacy_sql: Some(false),
+            parameter_mode: Some("NAMED".to_string()),
+            query_parameters: Some(self.parameters()),
+            ..Default::default()
+        }
+    }
+
+    fn group_column(&self) -> &'static str {
+        match self.group_by {
+            CostGroupBy::Service => "service.description",
+            CostGroupBy::Sku => "CONCAT(service.description, \" / \", sku.description)",
+            CostGroupBy::Label(_) => {
+                "(SELECT value FROM UNNEST(labels) WHERE key = @label_key LIMIT 1)"
+            }
+            CostGroupBy::Location => "IFNULL(location.region, location.location)",
+            CostGroupBy::Project => "project.id",
+        }
+    }
+}
+
+fn parameter_type(type_: &str) -> QueryParameterType {
+    QueryParameterType {
+        type_: Some(type_.to_string()),
+        ..Default::default()
+    }
+}
+
+fn scalar_parameter(name: &str, type_: &str, value: String) -> QueryParameter {
+    QueryParameter {
+        name: Some(name.to_string()),
+        parameter_type: Some(parameter_type(type_)),
+        parameter_value: Some(QueryParameterValue {
+            value: Some(value),
+            ..Default::default()
+        }),
+    }
+}
+
+// `project.dataset.table` の形式 (プロジェクトには `example.com:project` のようなドメインも付けられる)
+pub fn validate_table_id(table: &str) -> Result<()> {
+    let parts: Vec<_> = table.rsplitn(3, '.').collect();
+    let valid = match parts.as_slice() {
+        [table, dataset, project] => {
+            let (domain, project) = match project.split_once(':') {
+                Some((domain, project)) => (Some(domain), project),
+                None => (None, *project),
+            };
+            domain.map_or(true, |domain| {
+                !domain.is_empty()
+                    && domain.chars().all(|c| {
+                        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.'
+                    })
+            }) && !project.is_empty()
+                && project
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                && !dataset.is_empty()
+                && dataset
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !table.is_empty()
+                && table
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+        _ => false,
+    };
+    if !valid {
+        Err(anyhow!(
+            "Invalid BigQuery table: {} (expected project.dataset.table)",
+            table
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn range() -> DateRange {
+        DateRange::new(
+            NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn scalar_values(parameters: &[QueryParameter]) -> Vec<(String, String, String)> {
+        parameters
+            .iter()
+            .filter_map(|p| {
+                let value = p.parameter_value.as_ref()?.value.clone()?;
+                let type_ = p.parameter_type.as_ref()?.type_.clone()?;
+                Some((p.name.clone()?, type_, value))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sql_for_all_projects_by_service() {
+        let query = CostQuery::new("proj.billing.export", range()).unwrap();
+        let sql = query.sql();
+        assert!(sql.contains("SELECT project.id, service.description, cost,"));
+        assert!(sql.contains("FROM `proj.billing.export`"));
+        assert!(sql.contains("TIMESTAMP(@from_date) <= TIMESTAMP_TRUNC(_PARTITIONTIME, DAY)"));
+        assert!(
+            sql.contains("TIMESTAMP_TRUNC(_PARTITIONTIME, DAY) < TIMESTAMP(@to_date_exclusive)")
+        );
+        assert!(!sql.contains("@project_ids"));
+        assert!(!sql.contains("@label_key"));
+
+        assert_eq!(
+            scalar_values(&query.parameters()),
+            vec![
+                (
+                    "from_date".to_string(),
+                    "DATE".to_string(),
+                    "2023-12-31".to_string()
+                ),
+                (
+                    "to_date_exclusive".to_string(),
+                    "DATE".to_string(),
+                    "2024-01-02".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_for_project_list() {
+        let projects = CostProjects::List(vec!["a".to_string(), "b-2".to_string()]);
+        let query = CostQuery::new("proj.billing.export", range())
+            .unwrap()
+            .projects(&projects);
+        assert!(query
+            .sql()
+            .ends_with("\nAND project.id IN UNNEST(@project_ids)"));
+
+        let parameters = query.parameters();
+        assert_eq!(parameters.len(), 3);
+        let project_ids = &parameters[2];
+        assert_eq!(project_ids.name.as_deref(), Some("project_ids"));
+        let type_ = project_ids.parameter_type.as_ref().unwrap();
+        assert_eq!(type_.type_.as_deref(), Some("ARRAY"));
+        assert_eq!(
+            type_
+                .array_type
+                .as_ref()
+                .and_then(|t| t.as_ref())
+                .and_then(|t| t.type_.as_deref()),
+            Some("STRING")
+        );
+        let values: Vec<_> = project_ids
+            .parameter_value
+            .as_ref()
+            .and_then(|v| v.array_values.as_ref())
+            .unwrap()
+            .iter()
+            .map(|v| v.value.clone().unwrap())
+            .collect();
+        assert_eq!(values, vec!["a", "b-2"]);
+    }
+
+    #[test]
+    fn group_columns() {
+        let cases = [
+            (CostGroupBy::Service, "service.description"),
+            (
+                CostGroupBy::Sku,
+                "CONCAT(service.description, \" / \", sku.description)",
+            ),
+            (
+                CostGroupBy::Location,
+                "IFNULL(location.region, location.location)",
+            ),
+            (CostGroupBy::Project, "project.id, project.id"),
+            (
+                CostGroupBy::Label("team".to_string()),
+                "(SELECT value FROM UNNEST(labels) WHERE key = @label_key LIMIT 1)",
+            ),
+        ];
+        for (group_by, column) in cases {
+            let query = CostQuery::new("proj.billing.export", range())
+                .unwrap()
+                .group_by(&group_by);
+            assert!(query.sql().contains(column), "{:?}", group_by);
+        }
+    }
+
+    #[test]
+    fn label_key_is_a_parameter() {
+        let group_by = CostGroupBy::Label("team".to_string());
+        let query = CostQuery::new("proj.billing.export", range())
+            .unwrap()
+            .group_by(&group_by);
+        assert!(!query.sql().contains("team"));
+        assert!(scalar_values(&query.parameters()).contains(&(
+            "label_key".to_string(),
+            "STRING".to_string(),
+            "team".to_string()
+        )));
+    }
+
+    #[test]
+    fn valid_table_ids() {
+        for table in [
+            "my-project.billing.gcp_billing_export_v1_0123AB",
+            "example.com:my-project.billing.export",
+            "p1.d.t-1",
+        ] {
+            assert!(validate_table_id(table).is_ok(), "{}", table);
+        }
+    }
+
+    #[test]
+    fn invalid_table_ids() {
+        for table in [
+            "",
+            "billing.export",
+            "a.b.c.d",
+            "Project.billing.export",
+            "proj.bill-ing.export",
+            "proj.billing.",
+            ":proj.billing.export",
+            "x` OR 1=1 --:proj.ds.tbl",
+            "proj.ds.tbl` WHERE 1=1 --",
+            "exa mple.com:proj.ds.tbl",
+            "Example.com:proj.ds.tbl",
+        ] {
+            assert!(validate_table_id(table).is_err(), "{}", table);
+        }
+    }
+}
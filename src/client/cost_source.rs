@@ -0,0 +1,231 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::model::{
+    config::{Config, CostBasis},
+    currency::Currency,
+    date_range::DateRange,
+    gcp_cost::{
+        CostAmount, CostReport, CostRow, DailyCostReport, DateToServiceAmountMap,
+        DateToServiceCostMap, ProjectToDailyServiceAmountMap, ServiceToCostReportMap,
+    },
+};
+
+// コストデータの取得元 (BigQuery の請求エクスポート、ローカルのファイルなど)
+// 実装は期間内の行を返すだけで、通貨の換算や集計はここで共通に行う
+#[async_trait]
+pub trait CostSource: Send + Sync {
+    fn config(&self) -> &Config;
+
+    // 期間内の行 (プロジェクトの絞り込みと COST_GROUP_BY によるグループ化は済ませておく)
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>>;
+
+    // 設定されたプロジェクト毎の日次レポート (エクスポートがまだ無い日は service_to_cost が空になる)
+    async fn get_project_cost(
+        &self,
+        target_date: NaiveDate,
+    ) -> Result<BTreeMap<String, DailyCostReport>> {
+        let (currency, project_to_amount) = self
+            .get_project_daily_service_amount(&daily_report_range(target_date)?)
+            .await?;
+        let project_to_report = project_to_amount
+            .into_iter()
+            .map(|(project, date_to_service_amount)| {
+                (
+                    project,
+                    build_daily_cost_report(
+                        date_to_service_amount,
+                        target_date,
+                        self.config().cost_basis,
+                        currency.clone(),
+                    ),
+                )
+            })
+            .filter(|(_, report)| !report.service_to_cost.is_empty())
+            .collect();
+        Ok(project_to_report)
+    }
+
+    // 期間内の日別・サービス別コスト (設定された全プロジェクトの合計)
+    async fn get_daily_service_cost(
+        &self,
+        range: &DateRange,
+    ) -> Result<(Currency, DateToServiceCostMap)> {
+        let (currency, project_to_amount) = self.get_project_daily_service_amount(range).await?;
+        let mut date_to_service_amount = DateToServiceAmountMap::new();
+        for (_, project_amount) in project_to_amount {
+            merge_amount(&mut date_to_service_amount, project_amount);
+        }
+        Ok((
+            currency,
+            to_cost_map(&date_to_service_amount, self.config().cost_basis),
+        ))
+    }
+
+    // 期間内のプロジェクト別・日別・サービス別の金額と、その通貨
+    async fn get_project_daily_service_amount(
+        &self,
+        range: &DateRange,
+    ) -> Result<(Currency, ProjectToDailyServiceAmountMap)> {
+        let config = self.config();
+        let mut project_to_amount = ProjectToDailyServiceAmountMap::new();
+        let mut report_currency: Option<Currency> = None;
+        for row in self.get_cost_rows(range).await? {
+            let (currency, rate) = convert_currency(config, row.currency)?;
+            match &report_currency {
+                Some(report_currency) if *report_currency != currency => Err(anyhow!(
+                    "Multiple currencies in the cost data ({}, {}); set COST_DISPLAY_CURRENCY and COST_EXCHANGE_RATES",
+                    report_currency,
+                    currency
+                ))?,
+                Some(_) => {}
+                None => report_currency = Some(currency),
+            }
+
+            let service_map = project_to_amount
+                .entry(row.project)
+                .or_default()
+                .entry(row.date)
+                .or_default();
+            *service_map.entry(row.group).or_default() += CostAmount {
+                gross: row.gross * rate,
+                credits: row.credits * rate,
+            };
+        }
+        // println!("{:?}", project_to_amount);
+
+        let currency = report_currency
+            .or_else(|| config.cost_display_currency.clone())
+            .unwrap_or_default();
+        Ok((currency, project_to_amount))
+    }
+}
+
+// 複数の取得元 (GCP と AWS など) の行をまとめて、１つのレポートにする
+pub struct MultiCostSource {
+    config: Arc<Config>,
+    sources: Vec<Arc<dyn CostSource>>,
+}
+
+impl MultiCostSource {
+    pub fn new(config: Arc<Config>, sources: Vec<Arc<dyn CostSource>>) -> Self {
+        Self { config, sources }
+    }
+}
+
+#[async_trait]
+impl CostSource for MultiCostSource {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn get_cost_rows(&self, range: &DateRange) -> Result<Vec<CostRow>> {
+        let mut rows = vec![];
+        for source in &self.sources {
+            rows.extend(source.get_cost_rows(range).await?);
+        }
+        Ok(rows)
+    }
+}
+
+// 表示通貨が設定されている場合は、固定レートでその通貨に換算する
+fn convert_currency(config: &Config, currency: Currency) -> Result<(Currency, f64)> {
+    match &config.cost_display_currency {
+        Some(display) if *display != currency => {
+            let rate = config
+                .cost_exchange_rates
+                .get(currency.code())
+                .ok_or(anyhow!(
+                    "No exchange rate from {} to {} in COST_EXCHANGE_RATES",
+                    currency,
+                    display
+                ))?;
+            Ok((display.clone(), *rate))
+        }
+        _ => Ok((currency, 1.0)),
+    }
+}
+
+fn merge_amount(to: &mut DateToServiceAmountMap, from: DateToServiceAmountMap) {
+    for (date, service_to_amount) in from {
+        let service_map = to.entry(date).or_default();
+        for (service, amount) in service_to_amount {
+            *service_map.entry(service).or_default() += amount;
+        }
+    }
+}
+
+fn to_cost_map(
+    date_to_service_amount: &DateToServiceAmountMap,
+    basis: CostBasis,
+) -> DateToServiceCostMap {
+    date_to_service_amount
+        .iter()
+        .map(|(date, service_to_amount)| {
+            let service_to_cost = service_to_amount
+                .iter()
+                .map(|(service, amount)| (service.clone(), amount.value(basis)))
+                .collect();
+            (*date, service_to_cost)
+        })
+        .collect()
+}
+
+// 対象日を含む月と、前日比のための前日 (月初の場合は前月末)
+fn daily_report_range(target_date: NaiveDate) -> Result<DateRange> {
+    Ok(DateRange::month_of(target_date)?.extend_to(&DateRange::last_days(target_date, 2)))
+}
+
+fn build_daily_cost_report(
+    date_to_service_amount: DateToServiceAmountMap,
+    target_date: NaiveDate,
+    basis: CostBasis,
+    currency: Currency,
+) -> DailyCostReport {
+    let yesterday_from_target_date = target_date - Duration::days(1);
+    let mut date_to_service_cost = to_cost_map(&date_to_service_amount, basis);
+    let cost_yesterday = date_to_service_cost
+        .get(&yesterday_from_target_date)
+        .cloned()
+        .unwrap_or_default();
+    // 月初の場合に比較のため取得した前月末の日は、月の日別コストに含めない
+    let date_to_service_cost =
+        date_to_service_cost.split_off(&target_date.with_day(1).unwrap_or(target_date));
+
+    let mut service_to_cost_report: ServiceToCostReportMap = ServiceToCostReportMap::new();
+    for (service, amount) in date_to_service_amount
+        .get(&target_date)
+        .into_iter()
+        .flatten()
+    {
+        let cost = amount.value(basis);
+        let cost_2day_ago = cost_yesterday.get(service);
+
+        let diff_cost = cost - cost_2day_ago.unwrap_or(&0.0);
+        let diff_rate = match cost_2day_ago {
+            Some(&cost_2day_ago) if cost_2day_ago != 0.0 => Some(cost / cost_2day_ago),
+            _ => None,
+        };
+
+        service_to_cost_report.insert(
+            service.to_string(),
+            CostReport {
+                diff_rate,
+                diff_cost,
+                cost,
+                gross_cost: amount.gross,
+                credits: amount.credits,
+            },
+        );
+    }
+    // println!("{:?}", service_to_cost_report);
+
+    DailyCostReport {
+        currency,
+        service_to_cost: service_to_cost_report,
+        date_to_service_cost,
+    }
+}
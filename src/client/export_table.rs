@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate};
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::Field,
+};
+
+// 列名から値への表 (空の値と NULL は含めない)
+pub type ExportRecord = HashMap<String, String>;
+
+pub fn parse_csv(data: &[u8]) -> Result<Vec<ExportRecord>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        // Excel などが出力する BOM を除く
+        .map(|header| header.trim_start_matches('\u{feff}').trim().to_string())
+        .collect();
+    let mut records = vec![];
+    for row in reader.records() {
+        let row = row?;
+        let record: ExportRecord = headers
+            .iter()
+            .zip(row.iter())
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(header, value)| (header.clone(), value.trim().to_string()))
+            .collect();
+        if !record.is_empty() {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+// map 型の列 (CUR 2.0 の resource_tags など) は `{列名}_{キー}` の列に展開する
+pub fn parse_parquet(data: Vec<u8>) -> Result<Vec<ExportRecord>> {
+    let reader = SerializedFileReader::new(bytes::Bytes::from(data))?;
+    let mut records = vec![];
+    for row in reader.get_row_iter(None)? {
+        let mut record = ExportRecord::new();
+        for (name, field) in row?.get_column_iter() {
+            match field {
+                Field::MapInternal(map) => {
+                    for (key, value) in map.entries() {
+                        if let (Some(key), Some(value)) = (field_value(key), field_value(value)) {
+                            record.insert(format!("{}_{}", name, key), value);
+                        }
+                    }
+                }
+                field => {
+                    if let Some(value) = field_value(field) {
+                        record.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn field_value(field: &Field) -> Option<String> {
+    let value = match field {
+        Field::Null => return None,
+        Field::Str(value) => value.clone(),
+        Field::Double(value) => value.to_string(),
+        Field::Float(value) => value.to_string(),
+        Field::Date(days) => {
+            (DateTime::UNIX_EPOCH.date_naive() + Duration::days(*days as i64)).to_string()
+        }
+        Field::TimestampMillis(millis) => DateTime::from_timestamp_millis(*millis)?.to_rfc3339(),
+        Field::TimestampMicros(micros) => DateTime::from_timestamp_micros(*micros)?.to_rfc3339(),
+        field => field.to_string(),
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+// 列名の候補のうち最初に値があるもの (大文字小文字は区別しない)
+pub fn get<'a>(record: &'a ExportRecord, columns: &[&str]) -> Option<&'a str> {
+    columns.iter().find_map(|column| {
+        record
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(column))
+            .map(|(_, value)| value.as_str())
+    })
+}
+
+// `2023-08-01T00:00:00Z`, `2023-08-01 00:00:00 UTC` (UTC の日付) や Azure の `08/01/2023`
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    value
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .or_else(|| {
+            let date = value.split([' ', 'T']).next()?;
+            NaiveDate::parse_from_str(date, "%m/%d/%Y").ok()
+        })
+}
+
+pub fn get_f64(record: &ExportRecord, columns: &[&str]) -> Result<f64> {
+    match get(record, columns) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Invalid {}: {}", columns[0], value)),
+        None => Ok(0.0),
+    }
+}
@@ -9,7 +9,7 @@ use async_openai::{
 };
 use serde_json::json;
 
-use crate::model::openai::PaperSummaryModel;
+use crate::model::openai::{PaperRelevanceModel, PaperSummaryModel};
 use crate::model::paper::PaperModel;
 use crate::model::{config::Config, openai::Engine};
 
@@ -17,7 +17,7 @@ pub struct OpenAiClient {
     config: Arc<Config>,
 }
 
-impl<'a> OpenAiClient {
+impl OpenAiClient {
     pub fn new(config: Arc<Config>) -> Self {
         Self { config }
     }
@@ -27,15 +27,15 @@ impl<'a> OpenAiClient {
         paper: &PaperModel,
         engine: &Engine,
     ) -> Result<PaperSummaryModel> {
-        let system_prompt = "
-You are a teacher with expertise in information education and technology.
-Do not write in English. 
-"
-        .trim();
-        let user_prompt_prefix = "
-Explain the following a paper in simple, plain, jargon-free Japanese.
-The output should be specified formatted."
-            .trim();
+        let language = &self.config.paper_language;
+        let system_prompt = format!(
+            "You are a teacher with expertise in information education and technology.\nWrite in {}.",
+            language
+        );
+        let user_prompt_prefix = format!(
+            "Explain the following a paper in simple, plain, jargon-free {}.\nThe output should be specified formatted.",
+            language
+        );
         let user_prompt = format!(
             "{}\ntitle:{}\nsummary:{}",
             user_prompt_prefix, paper.title, paper.summary
@@ -49,7 +49,7 @@ The output should be specified formatted."
             .messages([
                 ChatCompletionRequestMessageArgs::default()
                     .role(Role::System)
-                    .content(system_prompt)
+                    .content(&system_prompt)
                     .build()?,
                 ChatCompletionRequestMessageArgs::default()
                     .role(Role::User)
@@ -64,11 +64,11 @@ The output should be specified formatted."
                     "properties": {
                         "title": {
                             "type": "string",
-                            "description": "Title of the paper written in Japanese.",
+                            "description": format!("Title of the paper written in {}.", language),
                         },
                         "summary": {
                             "type": "array",
-                            "description": "Paper summary text written in Japanese. One element is a sentence when all the summary text is expressed in bullet points.",
+                            "description": format!("Paper summary text written in {}. One element is a sentence when all the summary text is expressed in bullet points.", language),
                             "items" : {
                               "type": "string",
                             },
@@ -94,4 +94,64 @@ The output should be specified formatted."
         }
         Err(Error::msg("Failed to get function_call answer"))
     }
+
+    // interests との関連度を 0 (無関係) から 10 (非常に関連) で採点する
+    pub async fn rate_relevance(
+        &self,
+        paper: &PaperModel,
+        interests: &str,
+        engine: &Engine,
+    ) -> Result<f64> {
+        let system_prompt = "
+You are a research assistant who selects papers worth reading for a team.
+"
+        .trim();
+        let user_prompt = format!(
+            "Rate how relevant the following paper is to these interests: {}\ntitle:{}\nsummary:{}",
+            interests, paper.title, paper.summary
+        );
+
+        let config = OpenAIConfig::new().with_api_key(&self.config.openai_api_key);
+        let client = Client::with_config(config);
+        let request = CreateChatCompletionRequestArgs::default()
+            .max_tokens(64u16)
+            .model(engine.to_string())
+            .messages([
+                ChatCompletionRequestMessageArgs::default()
+                    .role(Role::System)
+                    .content(system_prompt)
+                    .build()?,
+                ChatCompletionRequestMessageArgs::default()
+                    .role(Role::User)
+                    .content(user_prompt)
+                    .build()?,
+            ])
+            .functions([ChatCompletionFunctionsArgs::default()
+                .name("rate_relevance")
+                .description("Record the relevance score of the paper.")
+                .parameters(json!({
+                    "type": "object",
+                    "properties": {
+                        "score": {
+                            "type": "integer",
+                            "description": "Relevance from 0 (unrelated) to 10 (highly relevant).",
+                            "minimum": 0,
+                            "maximum": 10,
+                        },
+                    },
+                    "required": ["score"],
+                }))
+                .build()?])
+            .function_call(json!({ "name": "rate_relevance" }))
+            .build()?;
+
+        let response = client.chat().create(request).await?;
+        for choice in response.choices {
+            if let Some(f) = choice.message.function_call {
+                let score: PaperRelevanceModel = serde_json::from_str(&f.arguments)?;
+                return Ok(score.score.clamp(0.0, 10.0));
+            }
+        }
+        Err(Error::msg("Failed to get function_call answer"))
+    }
 }
@@ -0,0 +1,52 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    // 最後に成功した実行で検索した期間の終わり
+    searched_to: DateTime<Utc>,
+}
+
+// ARXIV_SINCE_LAST_RUN で使う、前回の実行で検索した期間を JSON ファイルに保存する
+pub struct JsonPaperCheckpoint {
+    path: PathBuf,
+    persist: bool,
+}
+
+impl JsonPaperCheckpoint {
+    // persist が false の場合はファイルを更新しない (dry-run 用)
+    pub fn new(path: impl AsRef<Path>, persist: bool) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            persist,
+        }
+    }
+
+    pub fn load(&self) -> Result<Option<DateTime<Utc>>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let checkpoint: Checkpoint = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        Ok(Some(checkpoint.searched_to))
+    }
+
+    pub fn save(&self, searched_to: DateTime<Utc>) -> Result<()> {
+        if !self.persist {
+            return Ok(());
+        }
+        // seen_paper と同じく、一時ファイルに書いてから置き換える
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(
+            &tmp_path,
+            serde_json::to_string_pretty(&Checkpoint { searched_to })?,
+        )?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
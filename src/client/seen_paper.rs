@@ -0,0 +1,57 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+
+pub trait SeenPaperStore: Send + Sync {
+    fn contains(&self, arxiv_id: &str) -> Result<bool>;
+    fn insert(&self, arxiv_id: &str) -> Result<()>;
+}
+
+// 投稿済みの arXiv ID を JSON 配列としてファイルに保存する
+pub struct JsonSeenPaperStore {
+    path: PathBuf,
+    persist: bool,
+    ids: Mutex<BTreeSet<String>>,
+}
+
+impl JsonSeenPaperStore {
+    // persist が false の場合はファイルを更新しない (dry-run 用)
+    pub fn new(path: impl AsRef<Path>, persist: bool) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let ids = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self {
+            path,
+            persist,
+            ids: Mutex::new(ids),
+        })
+    }
+}
+
+impl SeenPaperStore for JsonSeenPaperStore {
+    fn contains(&self, arxiv_id: &str) -> Result<bool> {
+        let ids = self.ids.lock().map_err(|e| anyhow!(e.to_string()))?;
+        Ok(ids.contains(arxiv_id))
+    }
+
+    fn insert(&self, arxiv_id: &str) -> Result<()> {
+        let mut ids = self.ids.lock().map_err(|e| anyhow!(e.to_string()))?;
+        if !ids.insert(arxiv_id.to_string()) || !self.persist {
+            return Ok(());
+        }
+
+        // 書き込み途中で落ちてもファイルが壊れないよう、一時ファイルに書いてから置き換える
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&*ids)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
@@ -1,149 +1,583 @@
-use std::sync::Arc;
+use std::{borrow::Cow, fs, sync::Arc};
 
-use chrono::NaiveDateTime;
-use serde_json::json;
+use chrono::NaiveDate;
 
-use crate::model::{
-    config::Config,
-    gcp_cost::ServiceToCostReportMap,
-    openai::{Engine, PaperSummaryModel},
-    paper::PaperModel,
+use crate::{
+    client::slack_api::SlackApi,
+    model::{
+        block_kit::{escape, Block, Element, Message, Text},
+        config::{Config, CostBasis, CostGroupBy},
+        currency::Currency,
+        date_range::DateRange,
+        gcp_cost::{
+            BudgetStatus, CostAnomaly, CostReport, DailyCostReport, MonthlyCostSummary,
+            ServiceToCostReportMap,
+        },
+        openai::{Engine, PaperSummaryModel},
+        paper::PaperModel,
+        slack::{PostMessageResponse, UploadFileRequest},
+    },
 };
 use anyhow::Result;
 
-static SLACK_POST_URL: &str = "https://slack.com/api/chat.postMessage";
+// dry-run で投稿したことにするメッセージの ts
+static DRY_RUN_TS: &str = "0000000000.000000";
+// まとめたダイジェストの１メッセージあたりの論文数 (１件３ブロックで、１メッセージ 50 ブロックまで)
+static MAX_DIGEST_PAPERS: usize = 15;
+// メッセージは 50 ブロックまでのため、コストの内訳はこの件数 (10 件ずつの section で 10 ブロック) まで表示する
+static MAX_COST_FIELDS: usize = 100;
+// 予測が予算のこの割合を超えたら警告にする
+static BUDGET_WARNING_RATE: f64 = 0.9;
+
+// コストレポートのヘッダーなど、レポート毎に異なる部分
+struct CostMessage<'a> {
+    title: &'a str,
+    project: Cow<'a, str>,
+    group_by: &'a CostGroupBy,
+    channel: &'a str,
+    currency: &'a Currency,
+    totals: Vec<String>,
+    remark: &'a str,
+    // 予算の状況などを表す絵文字 (タイトルの前に付ける)
+    status: Option<&'a str>,
+}
 
 pub struct SlackClient {
     config: Arc<Config>,
+    api: SlackApi,
 }
 
-impl<'a> SlackClient {
+impl SlackClient {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self {
+            api: SlackApi::new(Arc::clone(&config)),
+            config,
+        }
     }
 
+    // thread_ts を指定するとスレッド返信にする
     pub async fn post_message(
         &self,
         paper: &PaperModel,
         answer: &PaperSummaryModel,
         engine: &Engine,
+        thread_ts: Option<&str>,
     ) -> Result<()> {
-        let client = reqwest::Client::new();
+        let message = Message::new(&self.config.slack_channel, &paper.title)
+            .thread(thread_ts)
+            .blocks(paper_blocks(paper, answer))
+            .block(Block::actions(vec![Element::link_button(
+                "Read on arXiv",
+                &paper.url,
+            )]))
+            .block(Block::context(vec![Text::mrkdwn(
+                &self.paper_footer(engine),
+            )]));
 
-        let mut text = format!("*{}*\n", answer.title);
-        for s in &answer.summary {
-            text.push_str(&format!(" • {}\n", s));
-        }
+        self.post(&message).await?;
 
-        let info = format!(
-            "Powered by {}  / Running on {}\n",
-            engine.to_string(),
-            &self.config.platform
-        );
+        Ok(())
+    }
 
-        let post_body = json!({
-          "channel": self.config.slack_channel,
-          "attachments": [
-            {
-              "mrkdwn_in": ["text"],
-              "color": "#3560a6",
-              "title": paper.title,
-              "title_link": paper.url,
-              "text": text,
-              "footer": info,
+    // スレッド形式のダイジェストの親メッセージ (論文は post_message で ts にスレッド返信する)
+    pub async fn post_paper_digest_parent(&self, count: usize) -> Result<PostMessageResponse> {
+        let text = format!("Today's papers: {}", count);
+        let message = Message::new(&self.config.slack_channel, &text).block(Block::section(
+            Text::mrkdwn(&format!(":newspaper: *{}*  (see thread)", text)),
+        ));
+        self.post(&message).await
+    }
+
+    // 全ての論文を１つのメッセージにまとめたダイジェスト
+    // ブロック数の上限を超えないよう、MAX_DIGEST_PAPERS 件を超える場合は複数のメッセージに分ける
+    pub async fn post_paper_digest(
+        &self,
+        papers: &[(PaperModel, PaperSummaryModel)],
+        engine: &Engine,
+    ) -> Result<()> {
+        let text = format!("Today's papers: {}", papers.len());
+        for chunk in papers.chunks(MAX_DIGEST_PAPERS) {
+            let mut message = Message::new(&self.config.slack_channel, &text).block(
+                Block::section(Text::mrkdwn(&format!(":newspaper: *{}*", text))),
+            );
+            for (paper, answer) in chunk {
+                message = message
+                    .block(Block::divider())
+                    .blocks(paper_blocks(paper, answer));
             }
-         ]
-        });
+            let message =
+                message
+                    .block(Block::divider())
+                    .block(Block::context(vec![Text::mrkdwn(
+                        &self.paper_footer(engine),
+                    )]));
 
-        client
-            .post(SLACK_POST_URL)
-            .bearer_auth(&self.config.slack_bot_token)
-            .json(&post_body)
-            .send()
-            .await?;
+            self.post(&message).await?;
+        }
 
         Ok(())
     }
+
+    fn paper_footer(&self, engine: &Engine) -> String {
+        format!(
+            "Powered by {}  / Running on {}",
+            engine, &self.config.platform
+        )
+    }
+
     pub async fn post_daily_cost(
+        &self,
+        project: &str,
+        channel: &str,
+        group_by: &CostGroupBy,
+        report: &DailyCostReport,
+        target_date: NaiveDate,
+        budget_status: BudgetStatus,
+    ) -> Result<PostMessageResponse> {
+        let currency = &report.currency;
+        let monthly_total_str = format!(
+            "{}:  *{}*",
+            target_date.format("%Y/%m"),
+            currency.format(budget_status.month_to_date)
+        );
+        let daily_total_str = format!(
+            "{}:  {}{}",
+            target_date.format("%Y/%m/%d"),
+            currency.format(report.service_to_cost.values().map(|v| v.cost).sum::<f64>()),
+            self.format_cost_breakdown(&report.service_to_cost, currency)
+        );
+        let mut forecast_str = format!("Forecast:  {}", currency.format(budget_status.forecast));
+        let mut status = None;
+        if let Some(budget) = budget_status.budget.filter(|b| *b > 0.0) {
+            let forecast_rate = budget_status.forecast / budget;
+            forecast_str = format!(
+                "{} / Budget:  {} (used {:.0}%, forecast {:.0}%)",
+                forecast_str,
+                currency.format(budget),
+                budget_status.month_to_date / budget * 100.0,
+                forecast_rate * 100.0,
+            );
+            status = Some(match forecast_rate {
+                r if r >= 1.0 => ":red_circle:",
+                r if r >= BUDGET_WARNING_RATE => ":large_yellow_circle:",
+                _ => ":large_green_circle:",
+            });
+        }
+        let remark = "_※ Cost from 09:00 JST to 09:00 JST the following day (compared to the previous day)._";
+
+        let message = self.cost_message(
+            CostMessage {
+                title: "Cost Report",
+                project: Cow::Borrowed(project),
+                group_by,
+                channel,
+                currency,
+                totals: vec![monthly_total_str, daily_total_str, forecast_str],
+                remark,
+                status,
+            },
+            &report.service_to_cost,
+        );
+        self.post(&message).await
+    }
+
+    // 合計の後ろに付ける gross とクレジットの内訳 (クレジットが無い場合は付けない)
+    fn format_cost_breakdown(
+        &self,
+        service_to_cost: &ServiceToCostReportMap,
+        currency: &Currency,
+    ) -> String {
+        let gross: f64 = service_to_cost.values().map(|v| v.gross_cost).sum();
+        let credits: f64 = service_to_cost.values().map(|v| v.credits).sum();
+        if credits == 0.0 {
+            return "".to_string();
+        }
+        let basis = match self.config.cost_basis {
+            CostBasis::Net => "net",
+            CostBasis::Gross => "gross",
+        };
+        format!(
+            "  ({}; gross {}, credits {})",
+            basis,
+            currency.format(gross),
+            currency.format(credits)
+        )
+    }
+
+    pub async fn post_range_cost(
         &self,
         service_to_cost: ServiceToCostReportMap,
-        monthly_total: f64,
-        target_date: NaiveDateTime,
+        range: DateRange,
+        currency: &Currency,
     ) -> Result<()> {
-        let client = reqwest::Client::new();
+        let range_total_str = format!(
+            "{} - {}:  *{}*{}",
+            range.from.format("%Y/%m/%d"),
+            range.to.format("%Y/%m/%d"),
+            currency.format(service_to_cost.values().map(|v| v.cost).sum::<f64>()),
+            self.format_cost_breakdown(&service_to_cost, currency)
+        );
+        let remark = "_※ Cost from 09:00 JST on the first day to 09:00 JST the day after the last day (compared to the previous period of the same length)._";
 
-        let mut fields: Vec<_> = service_to_cost
-            .iter()
-            .map(|(k, v)| {
-                let cost = format!("${:.0}", v.cost);
-                let percent = v
-                    .diff_rate
-                    .and_then(|r| {
-                        let percent = r * 100.0 - 100.0;
-                        if percent > 0.0 {
-                            Some(format!("+{:.0}%", percent))
-                        } else {
-                            Some(format!("{:.0}%", percent))
-                        }
-                    })
-                    .unwrap_or("-".to_string());
-                json!( {
-                    "title": k,
-                    "value": format!("{} ({})", cost, percent),
-                    "short": true
-                })
-            })
-            .collect();
+        let message = self.cost_message(
+            CostMessage {
+                title: "Cost Report",
+                totals: vec![range_total_str],
+                remark,
+                ..self.default_cost_message(currency)
+            },
+            &service_to_cost,
+        );
+        self.post(&message).await?;
+
+        Ok(())
+    }
+
+    pub async fn post_weekly_cost(
+        &self,
+        service_to_cost: ServiceToCostReportMap,
+        week: DateRange,
+        currency: &Currency,
+    ) -> Result<()> {
+        let weekly_total_str = format!(
+            "{} - {}:  *{}*",
+            week.from.format("%Y/%m/%d"),
+            week.to.format("%Y/%m/%d"),
+            currency.format(service_to_cost.values().map(|v| v.cost).sum::<f64>())
+        );
+        let remark = "_※ Cost of the last 7 days (compared to the previous 7 days)._";
 
-        fields.sort_by(|a, b| a["title"].as_str().cmp(&b["title"].as_str()));
+        let message = self.cost_message(
+            CostMessage {
+                title: "Weekly Cost Report",
+                totals: vec![weekly_total_str],
+                remark,
+                ..self.default_cost_message(currency)
+            },
+            &service_to_cost,
+        );
+        self.post(&message).await?;
+
+        Ok(())
+    }
 
-        let title = format!("*Cost Report*");
+    pub async fn post_monthly_cost(
+        &self,
+        service_to_cost: ServiceToCostReportMap,
+        summary: MonthlyCostSummary,
+    ) -> Result<()> {
+        let currency = &summary.currency;
         let monthly_total_str = format!(
-            "{}:  *${:.0}*",
-            target_date.format("%Y/%m"),
-            monthly_total,
+            "{} ({}/{} days):  *{}*",
+            summary.month.from.format("%Y/%m"),
+            summary.elapsed.num_days(),
+            summary.month.num_days(),
+            currency.format(summary.total),
         );
-        let daily_total_str = format!(
-            "{}:  ${:.0}",
-            target_date.format("%Y/%m/%d"),
-            service_to_cost.values().map(|v| v.cost).sum::<f64>()
+        let forecast_rate =
+            (summary.previous_total != 0.0).then(|| summary.forecast / summary.previous_total);
+        let forecast_str = format!(
+            "Forecast:  {} (previous month {}, {})",
+            currency.format(summary.forecast),
+            currency.format(summary.previous_total),
+            format_percent(forecast_rate),
         );
+        let top_movers_str = format!(
+            "Top movers:  {}",
+            summary
+                .top_movers
+                .iter()
+                .map(|(service, report)| format!(
+                    "{} {}",
+                    service,
+                    currency.format_diff(report.diff_cost)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let remark = "_※ Actual cost of the month so far (forecast for the full month compared to the previous month)._";
 
-        let project = format!("Project:  {}", &self.config.gcp_project_id);
-        let remark = format!("\n_※ Cost from 09:00 JST to 09:00 JST the following day (compared to the previous day)._");
-        let pretext = format!(
-            "{}\n{}\n{}\n{}",
-            title, project, monthly_total_str, daily_total_str
+        let message = self.cost_message(
+            CostMessage {
+                title: "Monthly Cost Report",
+                totals: vec![monthly_total_str, forecast_str, top_movers_str],
+                remark,
+                ..self.default_cost_message(currency)
+            },
+            &service_to_cost,
         );
-        let footer = format!(
-            "Data sourced from {} / Running on {}\n",
-            &self.config.gcp_bigquery_cost_table, &self.config.platform
+        self.post(&message).await?;
+
+        Ok(())
+    }
+
+    pub async fn post_cost_anomaly(
+        &self,
+        anomalies: Vec<CostAnomaly>,
+        target_date: NaiveDate,
+        currency: &Currency,
+    ) -> Result<()> {
+        let mut pretext = format!(
+            ":rotating_light: *Cost Anomaly*  {}\nProject:  {}",
+            target_date.format("%Y/%m/%d"),
+            self.config.cost_project_label()
         );
-        fields.push(json!({
-              "value": remark,
-              "short": false
-        }));
-        let post_body = json!({
-          "channel": self.config.slack_channel,
-          "attachments": [
-            {
-              "mrkdwn_in": ["text"],
-              "pretext": pretext,
-              "color": "#cdcdcd",
-              "fields": fields,
-              "footer": footer,
-            }
-         ]
-        });
-        // println!("{}", post_body);
+        if let Some(mention) = &self.config.cost_alert.mention {
+            pretext = format!("{} {}", mention, pretext);
+        }
 
-        client
-            .post(SLACK_POST_URL)
-            .bearer_auth(&self.config.slack_bot_token)
-            .json(&post_body)
-            .send()
-            .await?;
+        // 上限を超える場合は増加額の大きいものから表示する
+        let mut shown: Vec<_> = anomalies.iter().collect();
+        shown.sort_by(|a, b| b.report.diff_cost.total_cmp(&a.report.diff_cost));
+        shown.truncate(MAX_COST_FIELDS);
+        let hidden = anomalies.len() - shown.len();
+        let fields = shown
+            .into_iter()
+            .map(|anomaly| {
+                Text::mrkdwn(&format!(
+                    "*{}*\n{} ({}, {})\n{}",
+                    escape(&anomaly.service),
+                    currency.format(anomaly.report.cost),
+                    currency.format_diff(anomaly.report.diff_cost),
+                    format_percent(anomaly.report.diff_rate),
+                    anomaly.reasons.join(", ")
+                ))
+            })
+            .collect();
+        let message = Message::new(
+            &self.config.slack_channel,
+            &format!("Cost anomaly detected in {} service(s)", anomalies.len()),
+        )
+        .block(Block::section(Text::mrkdwn(&pretext)))
+        .blocks(Block::fields(fields))
+        .block(Block::context(
+            [
+                if hidden > 0 {
+                    format!("_…and {} more_", hidden)
+                } else {
+                    "".to_string()
+                },
+                self.cost_footer(),
+            ]
+            .iter()
+            .filter(|text| !text.is_empty())
+            .map(|text| Text::mrkdwn(text))
+            .collect(),
+        ));
+        self.post(&message).await?;
 
         Ok(())
     }
+
+    fn default_cost_message<'a>(&'a self, currency: &'a Currency) -> CostMessage<'a> {
+        CostMessage {
+            title: "Cost Report",
+            project: Cow::Owned(self.config.cost_project_label()),
+            group_by: &self.config.cost_group_by,
+            channel: &self.config.slack_channel,
+            currency,
+            totals: vec![],
+            remark: "",
+            status: None,
+        }
+    }
+
+    fn cost_message(
+        &self,
+        message: CostMessage,
+        service_to_cost: &ServiceToCostReportMap,
+    ) -> Message {
+        let fields = cap_services(service_to_cost)
+            .into_iter()
+            .map(|(k, v)| {
+                Text::mrkdwn(&format!(
+                    "*{}*\n{} ({})",
+                    escape(&k),
+                    message.currency.format(v.cost),
+                    format_percent(v.diff_rate)
+                ))
+            })
+            .collect();
+
+        let title = match message.status {
+            Some(status) => format!("{} *{}*", status, message.title),
+            None => format!("*{}*", message.title),
+        };
+        let mut header = vec![title, format!("Project:  {}", message.project)];
+        if *message.group_by != CostGroupBy::Service {
+            header.push(format!("Group by:  {}", message.group_by));
+        }
+        // 通知に表示される代替テキスト
+        let text = format!(
+            "{} ({}): {}",
+            message.title,
+            message.project,
+            message
+                .totals
+                .first()
+                .map(String::as_str)
+                .unwrap_or_default()
+        );
+
+        Message::new(message.channel, &text)
+            .block(Block::section(Text::mrkdwn(&header.join("\n"))))
+            .block(Block::section(Text::mrkdwn(&message.totals.join("\n"))))
+            .block(Block::divider())
+            .blocks(Block::fields(fields))
+            .block(Block::context(
+                [message.remark, &self.cost_footer()]
+                    .into_iter()
+                    // Slack は空のテキストを受け付けない
+                    .filter(|text| !text.is_empty())
+                    .map(Text::mrkdwn)
+                    .collect(),
+            ))
+    }
+
+    fn cost_footer(&self) -> String {
+        format!(
+            "Data sourced from {} / Running on {}",
+            self.config.cost_source_label(),
+            &self.config.platform
+        )
+    }
+
+    async fn post(&self, message: &Message) -> Result<PostMessageResponse> {
+        if self.config.dry_run {
+            println!("[dry-run] chat.postMessage");
+            println!("{}", serde_json::to_string_pretty(message)?);
+            println!("[dry-run] preview:\n{}", render_preview(message));
+            return Ok(PostMessageResponse {
+                channel: message.channel.clone(),
+                ts: DRY_RUN_TS.to_string(),
+            });
+        }
+
+        self.api.post_message(message).await
+    }
+
+    // channel はチャンネル ID (chat.postMessage のレスポンスの channel) を渡す
+    pub async fn upload_file(
+        &self,
+        channel: &str,
+        filename: &str,
+        title: &str,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        if self.config.dry_run {
+            fs::write(filename, &data)?;
+            println!(
+                "[dry-run] upload {} ({} bytes) to {}: saved to {}",
+                title,
+                data.len(),
+                channel,
+                filename
+            );
+            return Ok(());
+        }
+
+        self.api
+            .upload_file(UploadFileRequest {
+                channel: channel.to_string(),
+                filename: filename.to_string(),
+                title: title.to_string(),
+                data,
+                thread_ts: None,
+            })
+            .await
+    }
+}
+
+// 名前順に並べ、MAX_COST_FIELDS を超える場合はコストの小さいものを Other にまとめる
+fn cap_services(service_to_cost: &ServiceToCostReportMap) -> Vec<(String, CostReport)> {
+    let mut services: Vec<_> = service_to_cost
+        .iter()
+        .map(|(service, report)| (service.clone(), report.clone()))
+        .collect();
+    let mut other = None;
+    if services.len() > MAX_COST_FIELDS {
+        services.sort_by(|(_, a), (_, b)| b.cost.total_cmp(&a.cost));
+        let rest = services.split_off(MAX_COST_FIELDS - 1);
+        let cost: f64 = rest.iter().map(|(_, r)| r.cost).sum();
+        let diff_cost: f64 = rest.iter().map(|(_, r)| r.diff_cost).sum();
+        let previous_cost = cost - diff_cost;
+        other = Some((
+            format!("Other ({} groups)", rest.len()),
+            CostReport {
+                diff_rate: (previous_cost != 0.0).then(|| cost / previous_cost),
+                diff_cost,
+                cost,
+                gross_cost: rest.iter().map(|(_, r)| r.gross_cost).sum(),
+                credits: rest.iter().map(|(_, r)| r.credits).sum(),
+            },
+        ));
+    }
+    services.sort_by(|(a, _), (b, _)| a.cmp(b));
+    services.extend(other);
+    services
+}
+
+// 論文のタイトル (リンク) と要約
+fn paper_blocks(paper: &PaperModel, answer: &PaperSummaryModel) -> Vec<Block> {
+    let mut text = format!("*{}*\n", answer.title);
+    for s in &answer.summary {
+        text.push_str(&format!(" • {}\n", s));
+    }
+    vec![
+        Block::section(Text::mrkdwn(&format!(
+            "*<{}|{}>*",
+            paper.url,
+            escape(&paper.title)
+        ))),
+        Block::section(Text::mrkdwn(escape(&text).trim_end())),
+    ]
+}
+
+fn format_percent(diff_rate: Option<f64>) -> String {
+    diff_rate
+        .map(|r| {
+            let percent = r * 100.0 - 100.0;
+            if percent > 0.0 {
+                format!("+{:.0}%", percent)
+            } else {
+                format!("{:.0}%", percent)
+            }
+        })
+        .unwrap_or("-".to_string())
+}
+
+// Slack での見た目を確認するためのプレーンテキスト表示
+fn render_preview(message: &Message) -> String {
+    let mut lines = vec![
+        format!("channel: {}", message.channel),
+        format!("text: {}", message.text),
+    ];
+    for block in &message.blocks {
+        match block {
+            Block::Section { text, fields } => {
+                if let Some(text) = text {
+                    lines.push(text.as_str().trim_end().to_string());
+                }
+                for field in fields {
+                    let field = match field.as_str().split_once('\n') {
+                        Some((title, value)) => format!("{}: {}", title, value.replace('\n', " ")),
+                        None => field.as_str().to_string(),
+                    };
+                    lines.push(format!("  {}", field));
+                }
+            }
+            Block::Context { elements } => {
+                for element in elements.iter().filter(|e| !e.as_str().is_empty()) {
+                    lines.push(format!("-- {}", element.as_str()));
+                }
+            }
+            Block::Divider => lines.push("---".to_string()),
+            Block::Actions { elements } => {
+                for Element::Button { text, url } in elements {
+                    lines.push(format!("[{}] {}", text.as_str(), url));
+                }
+            }
+        }
+    }
+    lines.join("\n")
 }
@@ -0,0 +1,166 @@
+#![expect(
+    dead_code,
+    reason = "the typed Slack Web API covers methods that no command calls yet"
+)]
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::model::{
+    block_kit::Message,
+    config::Config,
+    slack::{
+        CompleteUploadRequest, DeleteMessageRequest, GetUploadUrlRequest, GetUploadUrlResponse,
+        PostEphemeralRequest, PostEphemeralResponse, PostMessageResponse, ReactionsAddRequest,
+        RepliesRequest, RepliesResponse, SlackError, SlackResponse, UpdateMessageRequest,
+        UpdateMessageResponse, UploadFileRequest, UploadedFile, UsersInfoRequest,
+        UsersInfoResponse,
+    },
+};
+
+static SLACK_API_URL: &str = "https://slack.com/api";
+
+// Slack Web API の呼び出し (dry-run やメッセージの組み立ては SlackClient 側で行う)
+// 書き込み系のメソッドは JSON、読み取り系のメソッドは JSON の本文を受け付けないためフォームで送る
+pub struct SlackApi {
+    config: Arc<Config>,
+    http: reqwest::Client,
+}
+
+impl SlackApi {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn post_message(&self, message: &Message) -> Result<PostMessageResponse> {
+        self.call_json("chat.postMessage", message).await
+    }
+
+    pub async fn update_message(
+        &self,
+        req: &UpdateMessageRequest,
+    ) -> Result<UpdateMessageResponse> {
+        self.call_json("chat.update", req).await
+    }
+
+    pub async fn delete_message(&self, req: &DeleteMessageRequest) -> Result<()> {
+        self.call_json::<_, Value>("chat.delete", req).await?;
+        Ok(())
+    }
+
+    pub async fn post_ephemeral(
+        &self,
+        req: &PostEphemeralRequest,
+    ) -> Result<PostEphemeralResponse> {
+        self.call_json("chat.postEphemeral", req).await
+    }
+
+    pub async fn replies(&self, req: &RepliesRequest) -> Result<RepliesResponse> {
+        self.call_form("conversations.replies", req).await
+    }
+
+    pub async fn add_reaction(&self, req: &ReactionsAddRequest) -> Result<()> {
+        self.call_json::<_, Value>("reactions.add", req).await?;
+        Ok(())
+    }
+
+    pub async fn user_info(&self, req: &UsersInfoRequest) -> Result<UsersInfoResponse> {
+        self.call_form("users.info", req).await
+    }
+
+    // 公式 SDK の files.uploadV2 と同じく、取得した URL にファイルを送ってからチャンネルに共有する
+    // (スコープは files:write)
+    pub async fn upload_file(&self, req: UploadFileRequest) -> Result<()> {
+        let upload: GetUploadUrlResponse = self
+            .call_form(
+                "files.getUploadURLExternal",
+                &GetUploadUrlRequest {
+                    filename: req.filename,
+                    length: req.data.len(),
+                },
+            )
+            .await?;
+
+        let res = self
+            .http
+            .post(&upload.upload_url)
+            .body(req.data)
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            Err(SlackError::Http(res.status().as_u16()))?;
+        }
+
+        self.call_json::<_, Value>(
+            "files.completeUploadExternal",
+            &CompleteUploadRequest {
+                files: vec![UploadedFile {
+                    id: upload.file_id,
+                    title: req.title,
+                }],
+                channel_id: req.channel,
+                thread_ts: req.thread_ts,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn call_json<T: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &T,
+    ) -> Result<R> {
+        let req = self
+            .http
+            .post(format!("{}/{}", SLACK_API_URL, method))
+            .bearer_auth(&self.config.slack_bot_token)
+            .json(body);
+        self.send(req).await
+    }
+
+    async fn call_form<T: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &T,
+    ) -> Result<R> {
+        let req = self
+            .http
+            .post(format!("{}/{}", SLACK_API_URL, method))
+            .bearer_auth(&self.config.slack_bot_token)
+            .form(params);
+        self.send(req).await
+    }
+
+    async fn send<R: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<R> {
+        let res = req.send().await?;
+
+        let status = res.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = res
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            Err(SlackError::RateLimited { retry_after })?;
+        }
+        if !status.is_success() {
+            Err(SlackError::Http(status.as_u16()))?;
+        }
+
+        let body: Value = res.json().await?;
+        let slack_res: SlackResponse = serde_json::from_value(body.clone())?;
+        if !slack_res.ok {
+            Err(SlackError::from_code(
+                slack_res.error.as_deref().unwrap_or("unknown_error"),
+            ))?;
+        }
+        Ok(serde_json::from_value(body)?)
+    }
+}
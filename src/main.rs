@@ -1,32 +1,173 @@
+mod cli;
 mod client;
 mod model;
 mod usecase;
 
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use clap::Parser;
 
-async fn execute() -> Result<()> {
-    let config = Arc::new(model::config::load_config()?);
-    let openai_client = Arc::new(client::openai::OpenAiClient::new(Arc::clone(&config)));
-    let slack_client = Arc::new(client::slack::SlackClient::new(Arc::clone(&config)));
-    let bigquery_client = Arc::new(client::bigquery::BigqueryClient::new(Arc::clone(&config)));
-    let arxiv_client = Arc::new(client::arxiv::ArxivClient::new(Arc::clone(&config)));
+use model::config::Config;
+use usecase::paper::PaperUsecase;
 
-    let paper_usecase = Arc::new(usecase::paper::PaperUsecase::new(
-        Arc::clone(&slack_client),
-        Arc::clone(&arxiv_client),
-        Arc::clone(&openai_client),
+fn paper_usecase(config: Arc<Config>) -> Result<PaperUsecase> {
+    // arXiv の設定はコストのコマンドでは使わないので、論文の通知を始める前にだけ検証する
+    config.arxiv_search_query()?;
+    let seen_paper_store = Arc::new(client::seen_paper::JsonSeenPaperStore::new(
+        &config.seen_paper_path,
+        !config.dry_run,
+    )?);
+    let checkpoint = Arc::new(client::paper_checkpoint::JsonPaperCheckpoint::new(
+        &config.arxiv_window.checkpoint_path,
+        !config.dry_run,
     ));
+    Ok(PaperUsecase::new(
+        Arc::clone(&config),
+        Arc::new(client::slack::SlackClient::new(Arc::clone(&config))),
+        Arc::new(client::arxiv::ArxivClient::new(Arc::clone(&config))),
+        Arc::new(client::openai::OpenAiClient::new(Arc::clone(&config))),
+        seen_paper_store,
+        checkpoint,
+    ))
+}
+
+// フィードが失敗しても残りのフィードは実行してから、失敗したフィードをまとめてエラーにする
+async fn notify_paper_feeds(
+    cli: &cli::Cli,
+    config: &Config,
+    feeds_path: &str,
+    feed: Option<&str>,
+    dates: Option<(chrono::NaiveDate, chrono::NaiveDate)>,
+) -> Result<()> {
+    let mut feeds = model::paper_feed::load_paper_feeds(feeds_path)?;
+    if let Some(name) = feed {
+        feeds.retain(|feed| feed.name == name);
+        if feeds.is_empty() {
+            return Err(anyhow!("Unknown paper feed: {}", name));
+        }
+    }
+
+    // 設定の誤りは、どのフィードも実行する前にエラーにする
+    let feed_configs = feeds
+        .iter()
+        .map(|feed| {
+            let mut feed_config = config.for_feed(feed)?;
+            // --channel は全てのフィードに優先する
+            if let Some(channel) = &cli.channel {
+                feed_config.slack_channel = channel.clone();
+            }
+            Ok(feed_config)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut failed = vec![];
+    for (feed, feed_config) in feeds.into_iter().zip(feed_configs) {
+        println!("paper feed: {}", feed.name);
+        let result = match paper_usecase(Arc::new(feed_config)) {
+            Ok(usecase) => usecase.notify_paper(dates).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Error in paper feed {}: {:?}", feed.name, e);
+            failed.push(feed.name);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!("Failed paper feeds: {}", failed.join(", ")));
+    }
+    Ok(())
+}
+
+async fn execute(cli: cli::Cli) -> Result<()> {
+    let command = cli.command()?;
+    let mut config = model::config::load_config()?;
+    if let Some(channel) = &cli.channel {
+        config.slack_channel = channel.clone();
+    }
+    if let Some(group_by) = &cli.group_by {
+        config.cost_group_by = group_by.clone();
+    }
+    if let cli::Command::NotifyPaper {
+        digest: Some(digest),
+        ..
+    } = &command
+    {
+        config.paper_digest = *digest;
+    }
+    config.dry_run |= cli.dry_run;
+    let config = Arc::new(config);
+    let slack_client = Arc::new(client::slack::SlackClient::new(Arc::clone(&config)));
+    let mut cost_sources: Vec<Arc<dyn client::cost_source::CostSource>> = config
+        .cost_sources
+        .iter()
+        .map(|source| -> Arc<dyn client::cost_source::CostSource> {
+            match source {
+                model::config::CostSourceConfig::Bigquery => {
+                    Arc::new(client::bigquery::BigqueryClient::new(Arc::clone(&config)))
+                }
+                model::config::CostSourceConfig::File(path) => Arc::new(
+                    client::cost_file::FileCostSource::new(Arc::clone(&config), path),
+                ),
+                model::config::CostSourceConfig::AwsCur(path) => Arc::new(
+                    client::aws_cur::AwsCurCostSource::new(Arc::clone(&config), path),
+                ),
+                model::config::CostSourceConfig::Azure(path) => Arc::new(
+                    client::azure_cost::AzureCostSource::new(Arc::clone(&config), path),
+                ),
+            }
+        })
+        .collect();
+    let cost_source = match cost_sources.len() {
+        1 => cost_sources.remove(0),
+        _ => Arc::new(client::cost_source::MultiCostSource::new(
+            Arc::clone(&config),
+            cost_sources,
+        )),
+    };
     let cost_notification_usecase = Arc::new(usecase::cost::CostUsecase::new(
+        Arc::clone(&config),
         Arc::clone(&slack_client),
-        Arc::clone(&bigquery_client),
+        cost_source,
     ));
 
-    match config.cmd.as_str() {
-        "notify_paper" => paper_usecase.notify_paper().await?,
-        "notify_daily_cost" => cost_notification_usecase.notify_daily_cost().await?,
-        cmd => Err(anyhow::anyhow!("Unknown command: {}", cmd))?,
+    match command {
+        cli::Command::NotifyPaper { from, to, feed, .. } => {
+            match (&config.paper_feeds_path, feed) {
+                (Some(feeds_path), feed) => {
+                    notify_paper_feeds(&cli, &config, feeds_path, feed.as_deref(), from.zip(to))
+                        .await?
+                }
+                (None, Some(_)) => Err(anyhow!("--feed requires PAPER_FEEDS_PATH"))?,
+                (None, None) => {
+                    paper_usecase(Arc::clone(&config))?
+                        .notify_paper(from.zip(to))
+                        .await?
+                }
+            }
+        }
+        cli::Command::NotifyDailyCost {
+            date,
+            from,
+            to,
+            combined,
+        } => match (from, to) {
+            (Some(from), Some(to)) => {
+                cost_notification_usecase
+                    .notify_cost_range(from, to, combined)
+                    .await?
+            }
+            _ => cost_notification_usecase.notify_daily_cost(date).await?,
+        },
+        cli::Command::NotifyWeeklyCost { date } => {
+            cost_notification_usecase.notify_weekly_cost(date).await?
+        }
+        cli::Command::NotifyMonthlyCost { month } => {
+            cost_notification_usecase.notify_monthly_cost(month).await?
+        }
+        cli::Command::NotifyCostAnomaly { date } => {
+            cost_notification_usecase.notify_cost_anomaly(date).await?
+        }
     }
 
     Ok(())
@@ -34,9 +175,10 @@ async fn execute() -> Result<()> {
 
 #[tokio::main]
 async fn main() {
+    let cli = cli::Cli::parse();
     println!("Start Job");
 
-    match execute().await {
+    match execute(cli).await {
         Ok(_) => (),
         Err(e) => {
             eprintln!("Error: {:?}", e);
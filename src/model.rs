@@ -1,4 +1,10 @@
+pub mod arxiv_query;
+pub mod block_kit;
 pub mod config;
+pub mod currency;
+pub mod date_range;
+pub mod gcp_cost;
 pub mod openai;
 pub mod paper;
-pub mod gcp_cost;
+pub mod paper_feed;
+pub mod slack;
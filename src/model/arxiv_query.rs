@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+// https://arxiv.org/category_taxonomy のアーカイブ (カテゴリの `.` より前)
+static ARCHIVES: [&str; 20] = [
+    "astro-ph", "cond-mat", "cs", "econ", "eess", "gr-qc", "hep-ex", "hep-lat", "hep-ph", "hep-th",
+    "math", "math-ph", "nlin", "nucl-ex", "nucl-th", "physics", "q-bio", "q-fin", "quant-ph",
+    "stat",
+];
+
+// arXiv の検索構文を知らなくても書けるように、JSON で条件を組み立てる
+// `{"and": [{"category": "cs.CL"}, {"or": [{"title": "llm"}, {"abstract": "language model"}]}, {"not": {"title": "survey"}}]}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArxivSearch {
+    Title(String),
+    Abstract(String),
+    Author(String),
+    Category(String),
+    // タイトル、概要、著者などの全ての項目
+    All(String),
+    And(Vec<ArxivSearch>),
+    Or(Vec<ArxivSearch>),
+    // and の中でのみ使え、他の条件に一致する論文から除く
+    Not(Box<ArxivSearch>),
+}
+
+impl ArxivSearch {
+    // 検証してから arXiv API の search_query に変換する
+    pub fn to_query(&self) -> Result<String> {
+        match self {
+            ArxivSearch::Title(value) => field("ti", value),
+            ArxivSearch::Abstract(value) => field("abs", value),
+            ArxivSearch::Author(value) => field("au", value),
+            ArxivSearch::All(value) => field("all", value),
+            ArxivSearch::Category(category) => {
+                validate_category(category)?;
+                Ok(format!("cat:{}", category))
+            }
+            ArxivSearch::Or(terms) => {
+                if terms.is_empty() {
+                    return Err(anyhow!("\"or\" needs at least one condition"));
+                }
+                let queries = terms
+                    .iter()
+                    .map(|term| match term {
+                        ArxivSearch::Not(_) => Err(anyhow!("\"not\" can only be used in \"and\"")),
+                        term => term.to_query(),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(join(queries, "OR"))
+            }
+            ArxivSearch::And(terms) => {
+                let mut includes = vec![];
+                let mut excludes = vec![];
+                for term in terms {
+                    match term {
+                        ArxivSearch::Not(term) => excludes.push(term.to_query()?),
+                        term => includes.push(term.to_query()?),
+                    }
+                }
+                if includes.is_empty() {
+                    return Err(anyhow!(
+                        "\"and\" needs at least one condition other than \"not\""
+                    ));
+                }
+                let include = join(includes, "AND");
+                if excludes.is_empty() {
+                    return Ok(include);
+                }
+                Ok(format!("({} ANDNOT {})", include, join(excludes, "OR")))
+            }
+            ArxivSearch::Not(_) => Err(anyhow!("\"not\" can only be used in \"and\"")),
+        }
+    }
+}
+
+// `cs.CL` や `hep-th` のようなカテゴリ
+pub fn validate_category(category: &str) -> Result<()> {
+    let (archive, subject) = match category.split_once('.') {
+        Some((archive, subject)) => (archive, Some(subject)),
+        None => (category, None),
+    };
+    let valid_subject = subject.map_or(true, |subject| {
+        !subject.is_empty()
+            && subject
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    if !ARCHIVES.contains(&archive) || !valid_subject {
+        return Err(anyhow!(
+            "Unknown arXiv category: {} (e.g. cs.CL, stat.ML, hep-th)",
+            category
+        ));
+    }
+    Ok(())
+}
+
+// ARXIV_QUERY のように直接書かれた検索式は、括弧と引用符の対応だけを確かめる
+pub fn validate_raw_query(query: &str) -> Result<()> {
+    if query.trim().is_empty() {
+        return Err(anyhow!("arXiv query is empty"));
+    }
+    let mut depth = 0;
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                depth -= 1;
+                if depth < 0 {
+                    break;
+                }
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 || quoted {
+        return Err(anyhow!(
+            "Unbalanced parentheses or quotes in arXiv query: {}",
+            query
+        ));
+    }
+    Ok(())
+}
+
+// 空白を含む値はフレーズとして検索する
+fn field(prefix: &str, value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(anyhow!("Empty {} search term", prefix));
+    }
+    if value.contains(['"', '(', ')']) {
+        return Err(anyhow!(
+            "Search term must not contain quotes or parentheses: {}",
+            value
+        ));
+    }
+    if value.contains(char::is_whitespace) {
+        Ok(format!("{}:\"{}\"", prefix, value))
+    } else {
+        Ok(format!("{}:{}", prefix, value))
+    }
+}
+
+fn join(queries: Vec<String>, operator: &str) -> String {
+    if queries.len() == 1 {
+        return queries.into_iter().next().unwrap_or_default();
+    }
+    format!("({})", queries.join(&format!(" {} ", operator)))
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+
+// section の fields は１ブロックに 10 個まで
+static MAX_SECTION_FIELDS: usize = 10;
+
+// chat.postMessage の本文
+// text は通知やスクリーンリーダー向けの代替テキストで、blocks がある場合メッセージには表示されない
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub channel: String,
+    pub text: String,
+    pub blocks: Vec<Block>,
+    // 指定するとそのメッセージへのスレッド返信になる
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_ts: Option<String>,
+}
+
+impl Message {
+    pub fn new(channel: &str, text: &str) -> Self {
+        Self {
+            channel: channel.to_string(),
+            text: text.to_string(),
+            blocks: vec![],
+            thread_ts: None,
+        }
+    }
+
+    pub fn thread(mut self, thread_ts: Option<&str>) -> Self {
+        self.thread_ts = thread_ts.map(str::to_string);
+        self
+    }
+
+    pub fn block(mut self, block: Block) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    pub fn blocks(mut self, blocks: impl IntoIterator<Item = Block>) -> Self {
+        self.blocks.extend(blocks);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Section {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<Text>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<Text>,
+    },
+    Context {
+        elements: Vec<Text>,
+    },
+    Divider,
+    Actions {
+        elements: Vec<Element>,
+    },
+}
+
+impl Block {
+    pub fn section(text: Text) -> Self {
+        Block::Section {
+            text: Some(text),
+            fields: vec![],
+        }
+    }
+
+    // fields が多い場合は MAX_SECTION_FIELDS 個ずつの section に分ける
+    pub fn fields(fields: Vec<Text>) -> Vec<Self> {
+        fields
+            .chunks(MAX_SECTION_FIELDS)
+            .map(|chunk| Block::Section {
+                text: None,
+                fields: chunk.to_vec(),
+            })
+            .collect()
+    }
+
+    pub fn context(elements: Vec<Text>) -> Self {
+        Block::Context { elements }
+    }
+
+    pub fn divider() -> Self {
+        Block::Divider
+    }
+
+    pub fn actions(elements: Vec<Element>) -> Self {
+        Block::Actions { elements }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum Text {
+    #[serde(rename = "mrkdwn")]
+    Mrkdwn { text: String },
+    #[serde(rename = "plain_text")]
+    PlainText { text: String, emoji: bool },
+}
+
+impl Text {
+    pub fn mrkdwn(text: &str) -> Self {
+        Text::Mrkdwn {
+            text: text.to_string(),
+        }
+    }
+
+    pub fn plain(text: &str) -> Self {
+        Text::PlainText {
+            text: text.to_string(),
+            emoji: true,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Text::Mrkdwn { text } | Text::PlainText { text, .. } => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Element {
+    // URL を開くだけのボタン
+    Button { text: Text, url: String },
+}
+
+impl Element {
+    pub fn link_button(text: &str, url: &str) -> Self {
+        Element::Button {
+            text: Text::plain(text),
+            url: url.to_string(),
+        }
+    }
+}
+
+// mrkdwn で制御文字として扱われる &, <, > をエスケープする
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -1,11 +1,22 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use core::fmt;
 use dotenv::dotenv;
-use std::env;
+use std::{collections::HashMap, env, path::Path, str::FromStr};
+
+use super::{
+    arxiv_query::{validate_category, validate_raw_query, ArxivSearch},
+    currency::Currency,
+    openai::Engine,
+    paper_feed::PaperFeed,
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub cmd: String,
     pub arxiv_query: String,
+    // 指定した場合は arxiv_query の代わりに使う
+    pub arxiv_search: Option<ArxivSearch>,
+    pub arxiv_categories: Vec<String>,
+    pub arxiv_window: ArxivWindowConfig,
     pub openai_api_key: String,
     pub slack_bot_token: String,
     pub slack_channel: String,
@@ -13,21 +24,488 @@ pub struct Config {
     pub gcp_credential_path: Option<String>,
     pub gcp_project_id: String,
     pub gcp_bigquery_cost_table: String,
+    pub cost_sources: Vec<CostSourceConfig>,
+    pub s3: S3Config,
+    pub seen_paper_path: String,
+    pub paper_digest: PaperDigest,
+    pub paper_count: usize,
+    pub paper_ranking: PaperRankingConfig,
+    pub paper_language: String,
+    pub paper_engine: Engine,
+    pub paper_feeds_path: Option<String>,
+    pub dry_run: bool,
+    pub cost_alert: CostAlertConfig,
+    pub cost_projects: CostProjects,
+    pub cost_group_by: CostGroupBy,
+    pub cost_basis: CostBasis,
+    pub cost_display_currency: Option<Currency>,
+    pub cost_exchange_rates: HashMap<String, f64>,
+    pub cost_project_channels: HashMap<String, String>,
+    pub cost_monthly_budgets: HashMap<String, f64>,
+    pub cost_forecast_method: ForecastMethod,
+    pub cost_chart: bool,
+    pub cost_chart_font: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum CostSourceConfig {
+    // GCP_BIGQUERY_COST_TABLE の請求エクスポート
+    Bigquery,
+    // `file:exports/billing.csv` のように、請求エクスポートと同じ形の CSV / JSON ファイルを指定する
+    File(String),
+    // `aws-cur:s3://bucket/cur.parquet` のように、AWS Cost and Usage Report のファイルを指定する
+    AwsCur(String),
+    // `azure:exports/azure.csv` のように、Azure Cost Management のエクスポートを指定する
+    Azure(String),
+}
+
+// s3:// のファイルを読むための設定 (AWS_ENDPOINT_URL を指定すると S3 互換ストレージも使える)
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub endpoint: Option<String>,
+    pub region: String,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+}
+
+impl FromStr for CostSourceConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bigquery" => Ok(CostSourceConfig::Bigquery),
+            _ => match s.split_once(':') {
+                Some(("file", path)) if !path.is_empty() => {
+                    Ok(CostSourceConfig::File(path.to_string()))
+                }
+                Some(("aws-cur", path)) if !path.is_empty() => {
+                    Ok(CostSourceConfig::AwsCur(path.to_string()))
+                }
+                Some(("azure", path)) if !path.is_empty() => {
+                    Ok(CostSourceConfig::Azure(path.to_string()))
+                }
+                _ => Err(anyhow!(
+                    "Unknown cost source: {} (bigquery, file:<path>, aws-cur:<path> or azure:<path>)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CostProjects {
+    // 請求アカウントに含まれる全プロジェクト
+    All,
+    List(Vec<String>),
+}
+
+// コストを集計する単位 (ServiceToCostReportMap のキー)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CostGroupBy {
+    Service,
+    Sku,
+    // `label:team` のようにラベルのキーを指定する
+    Label(String),
+    Location,
+    Project,
+}
+
+impl FromStr for CostGroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "service" => Ok(CostGroupBy::Service),
+            "sku" => Ok(CostGroupBy::Sku),
+            "location" => Ok(CostGroupBy::Location),
+            "project" => Ok(CostGroupBy::Project),
+            _ => match s.strip_prefix("label:") {
+                Some(key)
+                    if !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+                {
+                    Ok(CostGroupBy::Label(key.to_string()))
+                }
+                _ => Err(anyhow!(
+                    "Unknown cost grouping: {} (service, sku, location, project or label:<key>)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CostGroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostGroupBy::Service => write!(f, "Service"),
+            CostGroupBy::Sku => write!(f, "SKU"),
+            CostGroupBy::Label(key) => write!(f, "Label {}", key),
+            CostGroupBy::Location => write!(f, "Location"),
+            CostGroupBy::Project => write!(f, "Project"),
+        }
+    }
+}
+
+// 要約した論文の投稿方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperDigest {
+    // 論文毎にチャンネルに投稿する
+    Separate,
+    // 件数だけの親メッセージに、論文毎のスレッド返信を付ける
+    Thread,
+    // 全ての論文を１つのメッセージにまとめる
+    Combined,
+}
+
+impl FromStr for PaperDigest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "separate" => Ok(PaperDigest::Separate),
+            "thread" => Ok(PaperDigest::Thread),
+            "combined" => Ok(PaperDigest::Combined),
+            _ => Err(anyhow!(
+                "Unknown paper digest: {} (separate, thread or combined)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostBasis {
+    // クレジット (確約利用割引、無料枠、プロモーションなど) 適用後。GCP コンソールの表示と一致する
+    Net,
+    Gross,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ForecastMethod {
+    // 月初からの日平均で残りの日数を埋める
+    Linear,
+    // 直近７日間の日平均で残りの日数を埋める
+    TrailingAverage,
+}
+
+// arXiv はインデックスへの反映が遅れるため、offset_days 日前までに投稿された論文を検索する
+#[derive(Debug, Clone)]
+pub struct ArxivWindowConfig {
+    pub offset_days: i64,
+    pub width_days: i64,
+    // 前回の実行で検索した期間の終わりから検索する (初回は offset_days と width_days の期間)
+    pub since_last_run: bool,
+    pub checkpoint_path: String,
+}
+
+// 検索結果から投稿する論文を選ぶための設定 (何も設定しない場合は投稿日時の新しい順)
+#[derive(Debug, Clone)]
+pub struct PaperRankingConfig {
+    // タイトルか概要に含まれるキーワード (大文字小文字は区別しない)
+    pub keywords: Vec<String>,
+    // 著者に含まれていれば優先する研究者
+    pub authors: Vec<String>,
+    // キーワードと著者で絞った候補を、さらに LLM で関連度を採点して並べ替える
+    pub llm: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CostAlertConfig {
+    pub increase_percent: Option<f64>,
+    pub increase_amount: Option<f64>,
+    pub z_score: Option<f64>,
+    pub z_score_days: i64,
+    pub mention: Option<String>,
 }
 
 pub fn load_config() -> Result<Config> {
     let _ = dotenv();
     let config = Config {
-        cmd: env::var("CMD")?,
         arxiv_query: env::var("ARXIV_QUERY")
             .unwrap_or("llm OR \"generative ai\" OR \"visual recognition\"".to_string()),
+        arxiv_search: match env::var("ARXIV_SEARCH") {
+            Ok(search) if !search.trim().is_empty() => Some(
+                serde_json::from_str(&search)
+                    .map_err(|e| anyhow!("Invalid ARXIV_SEARCH: {}", e))?,
+            ),
+            _ => None,
+        },
+        arxiv_categories: parse_list(&env::var("ARXIV_CATEGORIES").unwrap_or_default()),
+        arxiv_window: ArxivWindowConfig {
+            offset_days: parse_env("ARXIV_OFFSET_DAYS")?.unwrap_or(5),
+            width_days: parse_env("ARXIV_WINDOW_DAYS")?.unwrap_or(1),
+            since_last_run: env::var("ARXIV_SINCE_LAST_RUN")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            checkpoint_path: env::var("ARXIV_CHECKPOINT_PATH")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or("arxiv_checkpoint.json".to_string()),
+        },
         openai_api_key: env::var("OPENAI_API_KEY")?,
         slack_bot_token: env::var("SLACK_BOT_TOKEN")?,
         slack_channel: env::var("SLACK_CHANNEL")?,
         platform: env::var("CLOUD_RUN_EXECUTION").unwrap_or("UNKNOWN".to_string()),
         gcp_credential_path: env::var("GOOGLE_APPLICATION_CREDENTIALS").ok(),
-        gcp_project_id: env::var("GCP_PROJECT_ID")?,
-        gcp_bigquery_cost_table: env::var("GCP_BIGQUERY_COST_TABLE")?,
+        // COST_SOURCE が bigquery の場合のみ必要
+        gcp_project_id: env::var("GCP_PROJECT_ID").unwrap_or_default(),
+        gcp_bigquery_cost_table: env::var("GCP_BIGQUERY_COST_TABLE").unwrap_or_default(),
+        // `bigquery,aws-cur:s3://bucket/cur.parquet` のように複数指定すると合算する
+        cost_sources: match env::var("COST_SOURCE") {
+            Ok(sources) if !sources.trim().is_empty() => sources
+                .split(',')
+                .map(|source| source.trim().parse())
+                .collect::<Result<_>>()?,
+            _ => vec![CostSourceConfig::Bigquery],
+        },
+        s3: S3Config {
+            endpoint: env::var("AWS_ENDPOINT_URL").ok().filter(|v| !v.is_empty()),
+            region: env::var("AWS_REGION")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or("us-east-1".to_string()),
+            access_key_id: env::var("AWS_ACCESS_KEY_ID").ok().filter(|v| !v.is_empty()),
+            secret_access_key: env::var("AWS_SECRET_ACCESS_KEY")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            session_token: env::var("AWS_SESSION_TOKEN").ok().filter(|v| !v.is_empty()),
+        },
+        seen_paper_path: env::var("SEEN_PAPER_PATH").unwrap_or("seen_papers.json".to_string()),
+        paper_digest: parse_env("PAPER_DIGEST")?.unwrap_or(PaperDigest::Separate),
+        paper_count: parse_env("PAPER_COUNT")?.unwrap_or(2),
+        paper_ranking: PaperRankingConfig {
+            keywords: parse_list(&env::var("PAPER_KEYWORDS").unwrap_or_default()),
+            authors: parse_list(&env::var("PAPER_AUTHORS").unwrap_or_default()),
+            llm: env::var("PAPER_LLM_RANKING")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+        },
+        paper_language: env::var("PAPER_LANGUAGE")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or("Japanese".to_string()),
+        paper_engine: parse_env("PAPER_MODEL")?.unwrap_or(Engine::Gpt4),
+        paper_feeds_path: env::var("PAPER_FEEDS_PATH").ok().filter(|v| !v.is_empty()),
+        dry_run: env::var("DRY_RUN")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false),
+        cost_alert: CostAlertConfig {
+            increase_percent: parse_env("COST_ALERT_INCREASE_PERCENT")?,
+            increase_amount: parse_env("COST_ALERT_INCREASE_AMOUNT")?,
+            z_score: parse_env("COST_ALERT_Z_SCORE")?,
+            z_score_days: parse_env("COST_ALERT_Z_SCORE_DAYS")?.unwrap_or(7),
+            mention: env::var("COST_ALERT_MENTION")
+                .ok()
+                .filter(|v| !v.is_empty()),
+        },
+        cost_projects: match env::var("GCP_COST_PROJECT_IDS").as_deref() {
+            Ok("all") => CostProjects::All,
+            Ok(ids) if !ids.trim().is_empty() => CostProjects::List(
+                ids.split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect(),
+            ),
+            _ => match env::var("GCP_PROJECT_ID") {
+                Ok(id) if !id.is_empty() => CostProjects::List(vec![id]),
+                _ => CostProjects::All,
+            },
+        },
+        cost_group_by: parse_env("COST_GROUP_BY")?.unwrap_or(CostGroupBy::Service),
+        cost_basis: match env::var("COST_BASIS").as_deref() {
+            Ok("gross") => CostBasis::Gross,
+            Ok("net") | Ok("") | Err(_) => CostBasis::Net,
+            Ok(basis) => Err(anyhow!("Unknown COST_BASIS: {}", basis))?,
+        },
+        cost_display_currency: env::var("COST_DISPLAY_CURRENCY")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| Currency::new(&v)),
+        // `JPY=0.0067` のように、各通貨の１単位が表示通貨でいくらになるか
+        cost_exchange_rates: parse_key_values::<f64>(
+            "COST_EXCHANGE_RATES",
+            &env::var("COST_EXCHANGE_RATES").unwrap_or_default(),
+        )?
+        .into_iter()
+        .map(|(code, rate)| (Currency::new(&code).code().to_string(), rate))
+        .collect(),
+        cost_project_channels: parse_key_values(
+            "COST_PROJECT_CHANNELS",
+            &env::var("COST_PROJECT_CHANNELS").unwrap_or_default(),
+        )?,
+        cost_monthly_budgets: parse_key_values(
+            "COST_MONTHLY_BUDGETS",
+            &env::var("COST_MONTHLY_BUDGETS").unwrap_or_default(),
+        )?,
+        cost_forecast_method: match env::var("COST_FORECAST_METHOD").as_deref() {
+            Ok("trailing") => ForecastMethod::TrailingAverage,
+            Ok("linear") | Ok("") | Err(_) => ForecastMethod::Linear,
+            Ok(method) => Err(anyhow!("Unknown COST_FORECAST_METHOD: {}", method))?,
+        },
+        // ファイルのアップロードには Slack アプリの files:write スコープが必要
+        cost_chart: env::var("COST_CHART")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false),
+        cost_chart_font: env::var("COST_CHART_FONT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".to_string()),
     };
     Ok(config)
 }
+
+impl Config {
+    // フィード毎の設定 (投稿済みの論文とチェックポイントはフィード毎に別のファイルに保存する)
+    pub fn for_feed(&self, feed: &PaperFeed) -> Result<Config> {
+        let mut config = self.clone();
+        match (&feed.query, &feed.search) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "Paper feed {} has both query and search (use one of them)",
+                    feed.name
+                ))
+            }
+            (Some(query), None) => {
+                config.arxiv_query = query.clone();
+                config.arxiv_search = None;
+            }
+            (None, Some(search)) => config.arxiv_search = Some(search.clone()),
+            (None, None) => (),
+        }
+        if !feed.categories.is_empty() {
+            config.arxiv_categories = feed.categories.clone();
+        }
+        if let Some(max_papers) = feed.max_papers {
+            config.paper_count = max_papers;
+        }
+        if let Some(channel) = &feed.channel {
+            config.slack_channel = channel.clone();
+        }
+        if let Some(language) = &feed.language {
+            config.paper_language = language.clone();
+        }
+        if let Some(model) = &feed.model {
+            config.paper_engine = model
+                .parse()
+                .map_err(|e| anyhow!("Invalid model for paper feed {}: {}", feed.name, e))?;
+        }
+        if let Some(keywords) = &feed.keywords {
+            config.paper_ranking.keywords = keywords.clone();
+        }
+        if let Some(authors) = &feed.authors {
+            config.paper_ranking.authors = authors.clone();
+        }
+        config.seen_paper_path = feed_path(&self.seen_paper_path, &feed.name);
+        config.arxiv_window.checkpoint_path =
+            feed_path(&self.arxiv_window.checkpoint_path, &feed.name);
+        config
+            .arxiv_search_query()
+            .map_err(|e| anyhow!("Invalid search for paper feed {}: {}", feed.name, e))?;
+        Ok(config)
+    }
+
+    // arXiv API の search_query (`(ti:llm OR abs:"language model") AND (cat:cs.CL OR cat:cs.CV)`)
+    pub fn arxiv_search_query(&self) -> Result<String> {
+        let query = match &self.arxiv_search {
+            Some(search) => search.to_query()?,
+            None => {
+                validate_raw_query(&self.arxiv_query)?;
+                self.arxiv_query.clone()
+            }
+        };
+        if self.arxiv_categories.is_empty() {
+            return Ok(query);
+        }
+        for category in &self.arxiv_categories {
+            validate_category(category)?;
+        }
+        let categories = self
+            .arxiv_categories
+            .iter()
+            .map(|category| format!("cat:{}", category))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        Ok(format!("({}) AND ({})", query, categories))
+    }
+
+    // Slack のフッターに表示するコストデータの取得元
+    pub fn cost_source_label(&self) -> String {
+        self.cost_sources
+            .iter()
+            .map(|source| match source {
+                CostSourceConfig::Bigquery => self.gcp_bigquery_cost_table.as_str(),
+                // 署名付き URL のクエリ文字列は表示しない
+                CostSourceConfig::File(path)
+                | CostSourceConfig::AwsCur(path)
+                | CostSourceConfig::Azure(path) => path.split('?').next().unwrap_or(path),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // Slack に表示するコスト集計対象のプロジェクト
+    pub fn cost_project_label(&self) -> String {
+        match &self.cost_projects {
+            CostProjects::All => "All projects".to_string(),
+            CostProjects::List(projects) => projects.join(", "),
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(key: &str) -> Result<Option<T>> {
+    match env::var(key) {
+        Ok(value) if !value.is_empty() => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("Invalid value for {}: {}", key, value)),
+        _ => Ok(None),
+    }
+}
+
+// `seen_papers.json` -> `seen_papers.security.json`
+fn feed_path(path: &str, name: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, name, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, name),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// `llm,diffusion model` (空の要素は除く)
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// `project-a=1000,project-b=500`
+fn parse_key_values<T: FromStr>(key: &str, value: &str) -> Result<HashMap<String, T>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (k, v) =
+                entry
+                    .split_once('=')
+                    .ok_or(anyhow!("Invalid {} entry: {}", key, entry))?;
+            let v = v
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid {} entry: {}", key, entry))?;
+            Ok((k.trim().to_string(), v))
+        })
+        .collect()
+}
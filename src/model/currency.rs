@@ -0,0 +1,71 @@
+use core::fmt;
+
+// 請求エクスポートの currency 列に入る ISO 4217 の通貨コード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Currency(String);
+
+impl Currency {
+    pub fn new(code: &str) -> Self {
+        Self(code.trim().to_ascii_uppercase())
+    }
+
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+
+    fn symbol(&self) -> Option<&'static str> {
+        match self.code() {
+            "USD" => Some("$"),
+            "JPY" => Some("¥"),
+            "EUR" => Some("€"),
+            "GBP" => Some("£"),
+            "CNY" => Some("CN¥"),
+            "KRW" => Some("₩"),
+            "INR" => Some("₹"),
+            "AUD" => Some("A$"),
+            "CAD" => Some("CA$"),
+            "SGD" => Some("S$"),
+            _ => None,
+        }
+    }
+
+    // 補助単位の桁数 (ISO 4217)
+    fn decimals(&self) -> usize {
+        match self.code() {
+            "JPY" | "KRW" | "CLP" | "ISK" | "VND" | "IDR" => 0,
+            "BHD" | "KWD" | "OMR" | "TND" => 3,
+            _ => 2,
+        }
+    }
+
+    pub fn format(&self, amount: f64) -> String {
+        let sign = if amount < 0.0 { "-" } else { "" };
+        self.format_with_sign(sign, amount.abs())
+    }
+
+    // 増減額の表示 (正の場合も + を付ける)
+    pub fn format_diff(&self, diff: f64) -> String {
+        let sign = if diff < 0.0 { "-" } else { "+" };
+        self.format_with_sign(sign, diff.abs())
+    }
+
+    fn format_with_sign(&self, sign: &str, amount: f64) -> String {
+        match self.symbol() {
+            Some(symbol) => format!("{}{}{:.*}", sign, symbol, self.decimals(), amount),
+            None => format!("{}{:.*} {}", sign, self.decimals(), amount, self.code()),
+        }
+    }
+}
+
+// エクスポートが空の場合などに使う、Cloud Billing の既定の通貨
+impl Default for Currency {
+    fn default() -> Self {
+        Self::new("USD")
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
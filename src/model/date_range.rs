@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+// BigQueryへのコストエクスポートは下記の特徴がある
+// ・UTC時間の日付毎にパーティションが切られている -> 日本時間９時が切り替わり時刻
+// ・コストは１時間毎に集計されている
+// ・BigQueryへのコストレコードの追加は３〜５時間程のラグがある -> １２時〜１４時の間に追加される
+// そのため、日付は全て UTC のパーティション日 (日本時間９時〜翌日９時) を表す
+static EXPORT_LAG_HOURS: i64 = 6;
+
+// from, to を両端に含む日付の期間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Result<Self> {
+        if to < from {
+            Err(anyhow!("Invalid range: {} is after {}", from, to))?;
+        }
+        Ok(Self { from, to })
+    }
+
+    // day を含む月の初日から末日まで
+    pub fn month_of(day: NaiveDate) -> Result<Self> {
+        let from = day
+            .with_day(1)
+            .ok_or(anyhow!("Failed to get the start_of_month"))?;
+        let (year, month) = match from.month() {
+            12 => (from.year() + 1, 1),
+            month => (from.year(), month + 1),
+        };
+        let start_of_next_month =
+            NaiveDate::from_ymd_opt(year, month, 1).ok_or(anyhow!("Invalid month value"))?;
+        Ok(Self {
+            from,
+            to: start_of_next_month - Duration::days(1),
+        })
+    }
+
+    // to を最終日とする days 日間
+    pub fn last_days(to: NaiveDate, days: i64) -> Self {
+        Self {
+            from: to - Duration::days(days.max(1) - 1),
+            to,
+        }
+    }
+
+    // 直前の同じ長さの期間
+    pub fn previous(&self) -> Self {
+        Self::last_days(self.from - Duration::days(1), self.num_days())
+    }
+
+    // from を含む月の前月
+    pub fn previous_month(&self) -> Result<Self> {
+        Self::month_of(self.from.with_day(1).unwrap_or(self.from) - Duration::days(1))
+    }
+
+    // to を day までに切り詰めた期間 (day が from より前の場合は None)
+    pub fn until(&self, day: NaiveDate) -> Option<Self> {
+        (day >= self.from).then(|| Self {
+            from: self.from,
+            to: self.to.min(day),
+        })
+    }
+
+    // other も含む期間に広げる
+    pub fn extend_to(&self, other: &DateRange) -> Self {
+        Self {
+            from: self.from.min(other.from),
+            to: self.to.max(other.to),
+        }
+    }
+
+    pub fn num_days(&self) -> i64 {
+        (self.to - self.from).num_days() + 1
+    }
+
+    // BigQuery の `<` 条件に使う翌日
+    pub fn end_exclusive(&self) -> NaiveDate {
+        self.to + Duration::days(1)
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let to = self.to;
+        self.from.iter_days().take_while(move |day| *day <= to)
+    }
+}
+
+// エクスポートのラグを考慮して、全てのコストが揃っている最新のパーティション日
+pub fn latest_complete_day(now: DateTime<Utc>) -> NaiveDate {
+    (now - Duration::hours(EXPORT_LAG_HOURS)).date_naive() - Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn range(from: NaiveDate, to: NaiveDate) -> DateRange {
+        DateRange::new(from, to).unwrap()
+    }
+
+    #[test]
+    fn new_rejects_reversed_range() {
+        assert!(DateRange::new(date(2023, 7, 2), date(2023, 7, 1)).is_err());
+        assert!(DateRange::new(date(2023, 7, 1), date(2023, 7, 1)).is_ok());
+    }
+
+    #[test]
+    fn month_of_december() {
+        assert_eq!(
+            DateRange::month_of(date(2023, 12, 15)).unwrap(),
+            range(date(2023, 12, 1), date(2023, 12, 31))
+        );
+    }
+
+    #[test]
+    fn month_of_february() {
+        assert_eq!(
+            DateRange::month_of(date(2023, 2, 28)).unwrap(),
+            range(date(2023, 2, 1), date(2023, 2, 28))
+        );
+        assert_eq!(
+            DateRange::month_of(date(2024, 2, 1)).unwrap(),
+            range(date(2024, 2, 1), date(2024, 2, 29))
+        );
+        assert_eq!(
+            DateRange::month_of(date(1900, 2, 10)).unwrap().num_days(),
+            28
+        );
+        assert_eq!(
+            DateRange::month_of(date(2000, 2, 10)).unwrap().num_days(),
+            29
+        );
+    }
+
+    #[test]
+    fn previous_month_in_january() {
+        assert_eq!(
+            range(date(2024, 1, 1), date(2024, 1, 31))
+                .previous_month()
+                .unwrap(),
+            range(date(2023, 12, 1), date(2023, 12, 31))
+        );
+        assert_eq!(
+            range(date(2024, 3, 10), date(2024, 3, 20))
+                .previous_month()
+                .unwrap(),
+            range(date(2024, 2, 1), date(2024, 2, 29))
+        );
+    }
+
+    #[test]
+    fn previous() {
+        assert_eq!(
+            range(date(2024, 1, 1), date(2024, 1, 7)).previous(),
+            range(date(2023, 12, 25), date(2023, 12, 31))
+        );
+        assert_eq!(
+            range(date(2024, 3, 1), date(2024, 3, 1)).previous(),
+            range(date(2024, 2, 29), date(2024, 2, 29))
+        );
+    }
+
+    #[test]
+    fn until() {
+        let month = range(date(2023, 7, 1), date(2023, 7, 31));
+        assert_eq!(
+            month.until(date(2023, 7, 10)),
+            Some(range(date(2023, 7, 1), date(2023, 7, 10)))
+        );
+        assert_eq!(month.until(date(2023, 8, 5)), Some(month));
+        assert_eq!(
+            month.until(date(2023, 7, 1)),
+            Some(range(date(2023, 7, 1), date(2023, 7, 1)))
+        );
+        assert_eq!(month.until(date(2023, 6, 30)), None);
+    }
+
+    #[test]
+    fn days() {
+        let days: Vec<_> = range(date(2023, 12, 30), date(2024, 1, 2)).days().collect();
+        assert_eq!(
+            days,
+            vec![
+                date(2023, 12, 30),
+                date(2023, 12, 31),
+                date(2024, 1, 1),
+                date(2024, 1, 2)
+            ]
+        );
+        assert_eq!(range(date(2023, 7, 1), date(2023, 7, 1)).days().count(), 1);
+    }
+
+    #[test]
+    fn latest_complete_day_waits_for_the_export_lag() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 3, 1, h, m, 0).unwrap();
+        // 06:00 UTC までは前日分のエクスポートが揃っていない
+        assert_eq!(latest_complete_day(at(5, 59)), date(2024, 2, 28));
+        assert_eq!(latest_complete_day(at(6, 0)), date(2024, 2, 29));
+        assert_eq!(latest_complete_day(at(23, 59)), date(2024, 2, 29));
+    }
+}
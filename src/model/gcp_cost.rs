@@ -1,11 +1,98 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::AddAssign,
+};
 
-#[derive(Debug)]
+use chrono::NaiveDate;
+
+use super::{config::CostBasis, currency::Currency, date_range::DateRange};
+
+// cost, diff_cost, diff_rate は設定 (COST_BASIS) に応じて net か gross のどちらか
+#[derive(Debug, Default, Clone)]
 pub struct CostReport {
     pub diff_rate: Option<f64>,
     pub diff_cost: f64,
     pub cost: f64,
+    pub gross_cost: f64,
+    pub credits: f64,
 }
 
+// credits は割引などの合計で、負の値になる
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CostAmount {
+    pub gross: f64,
+    pub credits: f64,
+}
+
+impl CostAmount {
+    pub fn net(&self) -> f64 {
+        self.gross + self.credits
+    }
+
+    pub fn value(&self, basis: CostBasis) -> f64 {
+        match basis {
+            CostBasis::Net => self.net(),
+            CostBasis::Gross => self.gross,
+        }
+    }
+}
+
+impl AddAssign for CostAmount {
+    fn add_assign(&mut self, other: Self) {
+        self.gross += other.gross;
+        self.credits += other.credits;
+    }
+}
+
+// 取得元の１行をプロジェクト・集計単位 (COST_GROUP_BY)・日付にまとめたもの
+#[derive(Debug, Clone)]
+pub struct CostRow {
+    pub project: String,
+    pub group: String,
+    pub gross: f64,
+    pub credits: f64,
+    pub date: NaiveDate,
+    pub currency: Currency,
+}
 
 pub type ServiceToCostReportMap = HashMap<String, CostReport>;
+
+pub type DateToServiceCostMap = BTreeMap<NaiveDate, HashMap<String, f64>>;
+
+pub type DateToServiceAmountMap = BTreeMap<NaiveDate, HashMap<String, CostAmount>>;
+
+pub type ProjectToDailyServiceAmountMap = BTreeMap<String, DateToServiceAmountMap>;
+
+#[derive(Debug)]
+pub struct MonthlyCostSummary {
+    pub currency: Currency,
+    pub month: DateRange,
+    // 月初から実績のある最終日まで
+    pub elapsed: DateRange,
+    pub total: f64,
+    pub forecast: f64,
+    pub previous_total: f64,
+    pub top_movers: Vec<(String, CostReport)>,
+}
+
+#[derive(Debug)]
+pub struct CostAnomaly {
+    pub service: String,
+    pub report: CostReport,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct DailyCostReport {
+    pub currency: Currency,
+    pub service_to_cost: ServiceToCostReportMap,
+    // 対象日を含む月の日別・サービス別コスト
+    pub date_to_service_cost: DateToServiceCostMap,
+}
+
+#[derive(Debug)]
+pub struct BudgetStatus {
+    pub month_to_date: f64,
+    pub forecast: f64,
+    pub budget: Option<f64>,
+}
@@ -1,8 +1,9 @@
+use anyhow::{anyhow, Result};
 use core::fmt;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Engine {
     Gpt3_5Turbo,
     Gpt4,
@@ -17,8 +18,25 @@ impl fmt::Display for Engine {
     }
 }
 
+impl FromStr for Engine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "gpt-3.5-turbo" => Ok(Engine::Gpt3_5Turbo),
+            "gpt-4" => Ok(Engine::Gpt4),
+            _ => Err(anyhow!("Unknown model: {} (gpt-4 or gpt-3.5-turbo)", s)),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaperSummaryModel {
     pub title: String,
     pub summary: Vec<String>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct PaperRelevanceModel {
+    pub score: f64,
+}
@@ -1,8 +1,53 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime, Utc};
+
+use super::date_range::DateRange;
+
 #[derive(Debug)]
 pub struct PaperModel {
     pub url: String,
-    pub published: String,
     pub title: String,
     pub summary: String,
     pub authors: Vec<String>,
 }
+
+impl PaperModel {
+    // `http://arxiv.org/abs/2307.01234v2` -> `2307.01234`
+    pub fn arxiv_id(&self) -> String {
+        let id = self
+            .url
+            .split_once("/abs/")
+            .map(|(_, id)| id)
+            .unwrap_or(&self.url);
+        match id.rfind('v') {
+            Some(i) if i + 1 < id.len() && id[i + 1..].chars().all(|c| c.is_ascii_digit()) => {
+                id[..i].to_string()
+            }
+            _ => id.to_string(),
+        }
+    }
+}
+
+// 論文を検索する投稿日時の期間 (from 以上 to 未満)
+#[derive(Debug, Clone, Copy)]
+pub struct PaperWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl PaperWindow {
+    pub fn new(from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self> {
+        if to < from {
+            Err(anyhow!("Invalid window: {} is after {}", from, to))?;
+        }
+        Ok(Self { from, to })
+    }
+
+    // 両端の日を含む UTC の日付の期間
+    pub fn from_dates(range: &DateRange) -> Self {
+        Self {
+            from: range.from.and_time(NaiveTime::MIN).and_utc(),
+            to: range.end_exclusive().and_time(NaiveTime::MIN).and_utc(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use super::arxiv_query::ArxivSearch;
+
+// PAPER_FEEDS_PATH の JSON 配列の１要素 (省略した項目は環境変数の設定を使う)
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaperFeed {
+    // 投稿済みの論文とチェックポイントのファイル名にも使う
+    pub name: String,
+    // arXiv の検索式をそのまま書く場合は query、JSON で組み立てる場合は search
+    pub query: Option<String>,
+    pub search: Option<ArxivSearch>,
+    // `cs.LG` のような arXiv のカテゴリ (いずれかに含まれる論文に絞る)
+    #[serde(default)]
+    pub categories: Vec<String>,
+    pub max_papers: Option<usize>,
+    pub channel: Option<String>,
+    // 要約の言語 (`Japanese`, `English` など)
+    pub language: Option<String>,
+    // 要約に使うモデル (`gpt-4` または `gpt-3.5-turbo`)
+    pub model: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+}
+
+pub fn load_paper_feeds(path: impl AsRef<Path>) -> Result<Vec<PaperFeed>> {
+    let path = path.as_ref();
+    let feeds: Vec<PaperFeed> = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("Invalid paper feeds file {}: {}", path.display(), e))?;
+
+    let mut names = HashSet::new();
+    for feed in &feeds {
+        if feed.name.is_empty()
+            || !feed
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow!(
+                "Invalid paper feed name: {:?} (use letters, digits, _ and -)",
+                feed.name
+            ));
+        }
+        if !names.insert(feed.name.as_str()) {
+            return Err(anyhow!("Duplicate paper feed name: {}", feed.name));
+        }
+    }
+    Ok(feeds)
+}
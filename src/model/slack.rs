@@ -0,0 +1,200 @@
+#![expect(
+    dead_code,
+    reason = "responses of Slack methods that no command calls yet are kept complete"
+)]
+
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+use super::block_kit::Block;
+
+// Slack Web API は失敗時も HTTP 200 で `{"ok": false, "error": "..."}` を返す
+#[derive(Debug, Deserialize)]
+pub struct SlackResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+// chat.postMessage の本文は block_kit::Message
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostMessageResponse {
+    // チャンネル名で投稿した場合もチャンネル ID が返る
+    pub channel: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateMessageRequest {
+    pub channel: String,
+    pub ts: String,
+    pub text: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMessageResponse {
+    pub channel: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteMessageRequest {
+    pub channel: String,
+    pub ts: String,
+}
+
+// チャンネル内の user にだけ表示されるメッセージ
+#[derive(Debug, Serialize)]
+pub struct PostEphemeralRequest {
+    pub channel: String,
+    pub user: String,
+    pub text: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostEphemeralResponse {
+    pub message_ts: String,
+}
+
+// ts は親メッセージの ts
+#[derive(Debug, Serialize)]
+pub struct RepliesRequest {
+    pub channel: String,
+    pub ts: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RepliesResponse {
+    pub messages: Vec<SlackMessage>,
+    #[serde(default)]
+    pub has_more: bool,
+    pub response_metadata: Option<ResponseMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackMessage {
+    pub ts: String,
+    pub thread_ts: Option<String>,
+    pub user: Option<String>,
+    pub bot_id: Option<String>,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponseMetadata {
+    // 最後のページでは空文字
+    #[serde(default)]
+    pub next_cursor: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionsAddRequest {
+    pub channel: String,
+    pub timestamp: String,
+    // `white_check_mark` のようにコロンを除いた絵文字名
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsersInfoRequest {
+    pub user: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsersInfoResponse {
+    pub user: SlackUser,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackUser {
+    pub id: String,
+    pub name: String,
+    pub real_name: Option<String>,
+    pub tz: Option<String>,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+// files.uploadV2 (files.getUploadURLExternal と files.completeUploadExternal) でアップロードするファイル
+#[derive(Debug)]
+pub struct UploadFileRequest {
+    // チャンネル ID (チャンネル名は使えない)
+    pub channel: String,
+    pub filename: String,
+    pub title: String,
+    pub data: Vec<u8>,
+    pub thread_ts: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetUploadUrlRequest {
+    pub filename: String,
+    pub length: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetUploadUrlResponse {
+    pub upload_url: String,
+    pub file_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompleteUploadRequest {
+    pub files: Vec<UploadedFile>,
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_ts: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadedFile {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug)]
+pub enum SlackError {
+    InvalidAuth(String),
+    ChannelNotFound,
+    NotInChannel,
+    RateLimited { retry_after: Option<u64> },
+    Api(String),
+    Http(u16),
+}
+
+impl SlackError {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "invalid_auth" | "not_authed" | "account_inactive" | "token_revoked"
+            | "token_expired" => SlackError::InvalidAuth(code.to_string()),
+            "channel_not_found" => SlackError::ChannelNotFound,
+            "not_in_channel" => SlackError::NotInChannel,
+            "ratelimited" | "rate_limited" => SlackError::RateLimited { retry_after: None },
+            _ => SlackError::Api(code.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SlackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlackError::InvalidAuth(code) => write!(f, "Slack authentication failed: {}", code),
+            SlackError::ChannelNotFound => write!(f, "Slack channel not found"),
+            SlackError::NotInChannel => write!(f, "Slack bot is not in the channel"),
+            SlackError::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "Slack rate limited (retry after {}s)", secs),
+            SlackError::RateLimited { retry_after: None } => write!(f, "Slack rate limited"),
+            SlackError::Api(code) => write!(f, "Slack API error: {}", code),
+            SlackError::Http(status) => write!(f, "Slack HTTP error: {}", status),
+        }
+    }
+}
+
+impl std::error::Error for SlackError {}
@@ -1,3 +1,2 @@
 pub mod cost;
 pub mod paper;
-
@@ -1,42 +1,508 @@
-use crate::client::{bigquery::BigqueryClient, slack::SlackClient};
+use crate::{
+    client::{cost_chart::render_cost_chart, cost_source::CostSource, slack::SlackClient},
+    model::{
+        config::{Config, CostAlertConfig, CostGroupBy, CostProjects, ForecastMethod},
+        currency::Currency,
+        date_range::{latest_complete_day, DateRange},
+        gcp_cost::{
+            BudgetStatus, CostAnomaly, CostReport, DailyCostReport, DateToServiceCostMap,
+            MonthlyCostSummary, ServiceToCostReportMap,
+        },
+    },
+};
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, Result};
-use chrono::{Duration, NaiveDateTime, Utc};
+use chrono::{Duration, NaiveDate, Utc};
+
+static TOP_MOVERS: usize = 3;
 
 pub struct CostUsecase {
+    config: Arc<Config>,
     slack_client: Arc<SlackClient>,
-    bigquery_client: Arc<BigqueryClient>,
+    cost_source: Arc<dyn CostSource>,
 }
 
-impl<'a> CostUsecase {
-    pub fn new(slack_client: Arc<SlackClient>, bigquery_client: Arc<BigqueryClient>) -> Self {
+impl CostUsecase {
+    pub fn new(
+        config: Arc<Config>,
+        slack_client: Arc<SlackClient>,
+        cost_source: Arc<dyn CostSource>,
+    ) -> Self {
         Self {
+            config,
             slack_client,
-            bigquery_client,
+            cost_source,
         }
     }
 
-    pub async fn notify_daily_cost(&self) -> Result<()> {
-        // BigQueryへのコストエクスポートは下記の特徴があるため、呼び出しは日本時間で１５時以降が良い
-        // ・UTC時間の日付毎にパーティションが切られている -> 日本時間９時が切り替わり時刻
-        // ・コストは１時間毎に集計されている
-        // ・BigQueryへのコストレコードの追加は３〜５時間程のラグがある -> １２時〜１４時の間に追加される
+    pub async fn notify_daily_cost(&self, target_date: Option<NaiveDate>) -> Result<()> {
+        // エクスポートのラグがあるため、呼び出しは日本時間で１５時以降が良い (date_range 参照)
+        self.notify_cost_of_day(target_date.unwrap_or(latest_complete_day(Utc::now())))
+            .await
+    }
+
+    // エクスポートが遅れて失敗した日などを後から再送するためのもの
+    pub async fn notify_cost_range(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        combined: bool,
+    ) -> Result<()> {
+        let range = DateRange::new(from, to)?;
 
-        let yesterday = Utc::now().naive_utc() - Duration::days(1);
-        let target_date = NaiveDateTime::new(
-            yesterday.into(),
-            chrono::NaiveTime::from_hms_opt(0, 0, 0).ok_or(anyhow!("Failed to get target_date"))?,
-        );
+        if !combined {
+            for day in range.days() {
+                self.notify_cost_of_day(day).await?;
+            }
+            return Ok(());
+        }
+
+        // 期間全体を、同じ長さの直前の期間と比較する
+        let previous_range = range.previous();
+        let (currency, date_to_service_cost) = self
+            .cost_source
+            .get_daily_service_cost(&previous_range.extend_to(&range))
+            .await?;
+        if !has_cost(&date_to_service_cost, &range) {
+            println!("no cost data yet for {} - {}", range.from, range.to);
+            return Ok(());
+        }
+
+        let current = sum_by_service(&date_to_service_cost, &range);
+        let previous = sum_by_service(&date_to_service_cost, &previous_range);
+
+        self.slack_client
+            .post_range_cost(compare_cost(&current, &previous), range, &currency)
+            .await?;
+
+        Ok(())
+    }
 
-        let (service_to_cost_report, month_total) =
-            self.bigquery_client.get_cost(target_date).await?;
+    // target_date までの直近７日間を、その前の７日間と比較する
+    pub async fn notify_weekly_cost(&self, target_date: Option<NaiveDate>) -> Result<()> {
+        let week = DateRange::last_days(target_date.unwrap_or(latest_complete_day(Utc::now())), 7);
+        let previous_week = week.previous();
+
+        let (currency, date_to_service_cost) = self
+            .cost_source
+            .get_daily_service_cost(&previous_week.extend_to(&week))
+            .await?;
+        if !has_cost(&date_to_service_cost, &week) {
+            println!("no cost data yet for {} - {}", week.from, week.to);
+            return Ok(());
+        }
+
+        let current = sum_by_service(&date_to_service_cost, &week);
+        let previous = sum_by_service(&date_to_service_cost, &previous_week);
 
         self.slack_client
-            .post_daily_cost(service_to_cost_report, month_total, target_date)
+            .post_weekly_cost(compare_cost(&current, &previous), week, &currency)
             .await?;
 
         Ok(())
     }
+
+    // 月の途中の場合は、経過日数から月末時点のコストを線形に予測して前月と比較する
+    pub async fn notify_monthly_cost(&self, month: Option<NaiveDate>) -> Result<()> {
+        let latest_day = latest_complete_day(Utc::now());
+        let month = DateRange::month_of(month.unwrap_or(latest_day))?;
+        let previous_month = month.previous_month()?;
+        let elapsed = month.until(latest_day).ok_or(anyhow!(
+            "No cost data yet for {}",
+            month.from.format("%Y/%m")
+        ))?;
+
+        let (currency, date_to_service_cost) = self
+            .cost_source
+            .get_daily_service_cost(&previous_month.extend_to(&elapsed))
+            .await?;
+        if !has_cost(&date_to_service_cost, &elapsed) {
+            println!("no cost data yet for {}", month.from.format("%Y/%m"));
+            return Ok(());
+        }
+
+        let forecast_ratio = month.num_days() as f64 / elapsed.num_days() as f64;
+
+        let current = sum_by_service(&date_to_service_cost, &elapsed);
+        let forecast: HashMap<_, _> = current
+            .iter()
+            .map(|(service, cost)| (service.clone(), cost * forecast_ratio))
+            .collect();
+        let previous = sum_by_service(&date_to_service_cost, &previous_month);
+
+        let mut service_to_cost = compare_cost(&forecast, &previous);
+
+        let mut top_movers: Vec<_> = service_to_cost
+            .iter()
+            .map(|(service, report)| (service.clone(), report.clone()))
+            .collect();
+        top_movers.sort_by(|(_, a), (_, b)| b.diff_cost.abs().total_cmp(&a.diff_cost.abs()));
+        top_movers.truncate(TOP_MOVERS);
+
+        // 各サービスの cost は実績、増減は予測値と前月の比較
+        for (service, report) in service_to_cost.iter_mut() {
+            report.cost = current.get(service).copied().unwrap_or(0.0);
+        }
+
+        let summary = MonthlyCostSummary {
+            currency,
+            month,
+            elapsed,
+            total: current.values().sum(),
+            forecast: forecast.values().sum(),
+            previous_total: previous.values().sum(),
+            top_movers,
+        };
+        self.slack_client
+            .post_monthly_cost(service_to_cost, summary)
+            .await?;
+
+        Ok(())
+    }
+
+    // 閾値を超えたサービスがある場合のみ通知する
+    pub async fn notify_cost_anomaly(&self, target_date: Option<NaiveDate>) -> Result<()> {
+        let alert = &self.config.cost_alert;
+        if alert.increase_percent.is_none()
+            && alert.increase_amount.is_none()
+            && alert.z_score.is_none()
+        {
+            Err(anyhow!("No cost anomaly threshold is configured"))?;
+        }
+
+        let target_date = target_date.unwrap_or(latest_complete_day(Utc::now()));
+        // 対象日と、その前の z_score_days 日間
+        let range = DateRange::last_days(target_date, alert.z_score_days.max(1) + 1);
+
+        let (currency, date_to_service_cost) =
+            self.cost_source.get_daily_service_cost(&range).await?;
+
+        let anomalies = detect_anomalies(&date_to_service_cost, target_date, alert);
+        if anomalies.is_empty() {
+            println!("no cost anomaly");
+            return Ok(());
+        }
+
+        self.slack_client
+            .post_cost_anomaly(anomalies, target_date, &currency)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn notify_cost_of_day(&self, day: NaiveDate) -> Result<()> {
+        let project_to_report = self.cost_source.get_project_cost(day).await?;
+        if project_to_report.is_empty() {
+            println!("no cost data yet for {}", day);
+            return Ok(());
+        }
+
+        // AWS のアカウントや Azure のサブスクリプションはプロジェクトで絞り込まれないため、
+        // 結果が１プロジェクトだけの場合に限る
+        if let CostProjects::List(projects) = &self.config.cost_projects {
+            if projects.len() == 1 && project_to_report.len() == 1 {
+                for (project, report) in project_to_report {
+                    self.post_project_daily_cost(&project, &self.config.slack_channel, report, day)
+                        .await?;
+                }
+                return Ok(());
+            }
+        }
+
+        // 複数プロジェクトの場合はプロジェクト別の内訳と合計を投稿し、
+        // チャンネルが設定されているプロジェクトはそのチャンネルにサービス別の内訳も投稿する
+        let mut project_to_cost = ServiceToCostReportMap::new();
+        let mut date_to_project_cost = DateToServiceCostMap::new();
+        let mut currency = Currency::default();
+        let mut budget = Some(0.0);
+        for (project, report) in project_to_report {
+            currency = report.currency.clone();
+            let cost: f64 = report.service_to_cost.values().map(|r| r.cost).sum();
+            let diff_cost: f64 = report.service_to_cost.values().map(|r| r.diff_cost).sum();
+            let previous_cost = cost - diff_cost;
+            project_to_cost.insert(
+                project.clone(),
+                CostReport {
+                    diff_rate: (previous_cost != 0.0).then(|| cost / previous_cost),
+                    diff_cost,
+                    cost,
+                    gross_cost: report.service_to_cost.values().map(|r| r.gross_cost).sum(),
+                    credits: report.service_to_cost.values().map(|r| r.credits).sum(),
+                },
+            );
+            for (date, service_to_cost) in &report.date_to_service_cost {
+                *date_to_project_cost
+                    .entry(*date)
+                    .or_default()
+                    .entry(project.clone())
+                    .or_insert(0.0) += service_to_cost.values().sum::<f64>();
+            }
+            // 全プロジェクトに予算が設定されている場合のみ合計の予算を出す
+            budget = budget
+                .zip(self.config.cost_monthly_budgets.get(&project))
+                .map(|(total, budget)| total + budget);
+
+            if let Some(channel) = self.config.cost_project_channels.get(&project) {
+                self.post_project_daily_cost(&project, channel, report, day)
+                    .await?;
+            }
+        }
+
+        let budget_status = budget_status(
+            &date_to_project_cost,
+            day,
+            budget,
+            self.config.cost_forecast_method,
+        )?;
+        let report = DailyCostReport {
+            currency,
+            service_to_cost: project_to_cost,
+            date_to_service_cost: date_to_project_cost,
+        };
+        let project = self.config.cost_project_label();
+        let res = self
+            .slack_client
+            .post_daily_cost(
+                &project,
+                &self.config.slack_channel,
+                &CostGroupBy::Project,
+                &report,
+                day,
+                budget_status,
+            )
+            .await?;
+        self.post_cost_chart(&project, &res.channel, &report, day)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn post_project_daily_cost(
+        &self,
+        project: &str,
+        channel: &str,
+        report: DailyCostReport,
+        day: NaiveDate,
+    ) -> Result<()> {
+        let budget_status = budget_status(
+            &report.date_to_service_cost,
+            day,
+            self.config.cost_monthly_budgets.get(project).copied(),
+            self.config.cost_forecast_method,
+        )?;
+
+        let res = self
+            .slack_client
+            .post_daily_cost(
+                project,
+                channel,
+                &self.config.cost_group_by,
+                &report,
+                day,
+                budget_status,
+            )
+            .await?;
+        self.post_cost_chart(project, &res.channel, &report, day)
+            .await?;
+
+        Ok(())
+    }
+
+    // COST_CHART が有効な場合、日別コストのグラフをテキストのレポートと同じチャンネルにアップロードする
+    async fn post_cost_chart(
+        &self,
+        project: &str,
+        channel: &str,
+        report: &DailyCostReport,
+        day: NaiveDate,
+    ) -> Result<()> {
+        if !self.config.cost_chart {
+            return Ok(());
+        }
+
+        let title = format!("{}  {}", project, day.format("%Y/%m"));
+        let chart = render_cost_chart(&title, report, day, &self.config.cost_chart_font)?;
+        let filename = format!(
+            "cost-{}-{}.png",
+            project
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect::<String>(),
+            day
+        );
+        self.slack_client
+            .upload_file(channel, &filename, &title, chart)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn budget_status(
+    date_to_service_cost: &DateToServiceCostMap,
+    day: NaiveDate,
+    budget: Option<f64>,
+    method: ForecastMethod,
+) -> Result<BudgetStatus> {
+    Ok(BudgetStatus {
+        month_to_date: date_to_service_cost
+            .range(..=day)
+            .flat_map(|(_, costs)| costs.values())
+            .sum(),
+        forecast: forecast_month_total(date_to_service_cost, day, method)?,
+        budget,
+    })
+}
+
+// target_date までの実績から月末時点の合計を予測する
+fn forecast_month_total(
+    date_to_service_cost: &DateToServiceCostMap,
+    target_date: NaiveDate,
+    method: ForecastMethod,
+) -> Result<f64> {
+    let month = DateRange::month_of(target_date)?;
+    let days_in_month = month.num_days();
+    let elapsed_days = (target_date - month.from).num_days() + 1;
+    let daily_total = |day: &NaiveDate| {
+        date_to_service_cost
+            .get(day)
+            .map(|costs| costs.values().sum::<f64>())
+            .unwrap_or(0.0)
+    };
+
+    let month_to_date: f64 = DateRange::new(month.from, target_date)?
+        .days()
+        .map(|day| daily_total(&day))
+        .sum();
+    let daily_average = match method {
+        ForecastMethod::Linear => month_to_date / elapsed_days as f64,
+        ForecastMethod::TrailingAverage => {
+            // 月初から７日未満の場合は経過日数分の平均
+            let days = elapsed_days.min(7);
+            (0..days)
+                .map(|i| daily_total(&(target_date - Duration::days(i))))
+                .sum::<f64>()
+                / days as f64
+        }
+    };
+
+    Ok(month_to_date + daily_average * (days_in_month - elapsed_days) as f64)
+}
+
+// 期間内のサービス別合計
+fn sum_by_service(
+    date_to_service_cost: &DateToServiceCostMap,
+    range: &DateRange,
+) -> HashMap<String, f64> {
+    let mut service_to_cost = HashMap::new();
+    for (_, costs) in date_to_service_cost.range(range.from..=range.to) {
+        for (service, cost) in costs {
+            *service_to_cost.entry(service.clone()).or_insert(0.0) += cost;
+        }
+    }
+    service_to_cost
+}
+
+fn has_cost(date_to_service_cost: &DateToServiceCostMap, range: &DateRange) -> bool {
+    date_to_service_cost
+        .range(range.from..=range.to)
+        .any(|(_, costs)| !costs.is_empty())
+}
+
+// 前の期間にだけ存在するサービスも 0 として含める
+fn compare_cost(
+    current: &HashMap<String, f64>,
+    previous: &HashMap<String, f64>,
+) -> ServiceToCostReportMap {
+    current
+        .keys()
+        .chain(previous.keys())
+        .map(|service| {
+            let cost = current.get(service).copied().unwrap_or(0.0);
+            let previous_cost = previous.get(service).copied().unwrap_or(0.0);
+            let report = CostReport {
+                diff_rate: (previous_cost != 0.0).then(|| cost / previous_cost),
+                diff_cost: cost - previous_cost,
+                cost,
+                ..Default::default()
+            };
+            (service.clone(), report)
+        })
+        .collect()
+}
+
+// 前日比の増加率・増加額は両方設定されていれば両方を超えた場合、z-score はそれとは独立に判定する
+// (前日にコストの無かったサービスは増加率を超えたものとする)
+fn detect_anomalies(
+    date_to_service_cost: &DateToServiceCostMap,
+    target_date: NaiveDate,
+    alert: &CostAlertConfig,
+) -> Vec<CostAnomaly> {
+    let Some(target_costs) = date_to_service_cost.get(&target_date) else {
+        return vec![];
+    };
+    let previous_costs = date_to_service_cost.get(&(target_date - Duration::days(1)));
+
+    let mut anomalies = vec![];
+    for (service, &cost) in target_costs {
+        let previous_cost = previous_costs
+            .and_then(|costs| costs.get(service))
+            .copied()
+            .unwrap_or(0.0);
+        let report = CostReport {
+            diff_rate: (previous_cost != 0.0).then(|| cost / previous_cost),
+            diff_cost: cost - previous_cost,
+            cost,
+            ..Default::default()
+        };
+
+        let mut reasons = vec![];
+        let percent_exceeded = alert.increase_percent.map(|threshold| {
+            report
+                .diff_rate
+                .map_or(cost > 0.0, |r| r * 100.0 - 100.0 >= threshold)
+        });
+        let amount_exceeded = alert
+            .increase_amount
+            .map(|threshold| report.diff_cost >= threshold);
+        match (percent_exceeded, amount_exceeded) {
+            (Some(true), Some(true)) | (Some(true), None) | (None, Some(true)) => {
+                reasons.push("day-over-day increase".to_string())
+            }
+            _ => (),
+        }
+
+        // 対象日を除いた直近 N 日間の平均・標準偏差
+        let trailing: Vec<f64> = date_to_service_cost
+            .range(..target_date)
+            .rev()
+            .take(alert.z_score_days as usize)
+            .map(|(_, costs)| costs.get(service).copied().unwrap_or(0.0))
+            .collect();
+        let z_score = (trailing.len() >= 2)
+            .then(|| {
+                let n = trailing.len() as f64;
+                let mean = trailing.iter().sum::<f64>() / n;
+                let std = (trailing.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n).sqrt();
+                (std > 0.0).then(|| (cost - mean) / std)
+            })
+            .flatten();
+        if let (Some(threshold), Some(z)) = (alert.z_score, z_score) {
+            if z >= threshold {
+                reasons.push(format!("z-score {:.1} over {} days", z, trailing.len()));
+            }
+        }
+
+        if !reasons.is_empty() {
+            anomalies.push(CostAnomaly {
+                service: service.clone(),
+                report,
+                reasons,
+            });
+        }
+    }
+    anomalies.sort_by(|a, b| b.report.diff_cost.total_cmp(&a.report.diff_cost));
+    anomalies
 }
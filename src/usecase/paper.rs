@@ -1,63 +1,243 @@
 use crate::{
-    client::{arxiv::ArxivClient, openai::OpenAiClient, slack::SlackClient},
-    model,
+    client::{
+        arxiv::ArxivClient, openai::OpenAiClient, paper_checkpoint::JsonPaperCheckpoint,
+        seen_paper::SeenPaperStore, slack::SlackClient,
+    },
+    model::{
+        config::{Config, PaperDigest, PaperRankingConfig},
+        date_range::DateRange,
+        openai::{Engine, PaperSummaryModel},
+        paper::{PaperModel, PaperWindow},
+    },
 };
 
 use std::sync::Arc;
 
 use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use futures::future::join_all;
 use tokio::task;
 
+static TITLE_KEYWORD_SCORE: f64 = 2.0;
+static SUMMARY_KEYWORD_SCORE: f64 = 1.0;
+static AUTHOR_SCORE: f64 = 5.0;
+// LLM で採点する候補の数 (PAPER_COUNT の倍数)
+static LLM_CANDIDATES_PER_PAPER: usize = 5;
+
 pub struct PaperUsecase {
+    config: Arc<Config>,
     slack_client: Arc<SlackClient>,
     arxiv_client: Arc<ArxivClient>,
     openai_client: Arc<OpenAiClient>,
+    seen_paper_store: Arc<dyn SeenPaperStore>,
+    checkpoint: Arc<JsonPaperCheckpoint>,
 }
 
-impl<'a> PaperUsecase {
+impl PaperUsecase {
     pub fn new(
+        config: Arc<Config>,
         slack_client: Arc<SlackClient>,
         arxiv_client: Arc<ArxivClient>,
         openai_client: Arc<OpenAiClient>,
+        seen_paper_store: Arc<dyn SeenPaperStore>,
+        checkpoint: Arc<JsonPaperCheckpoint>,
     ) -> Self {
         Self {
+            config,
             slack_client,
             arxiv_client,
             openai_client,
+            seen_paper_store,
+            checkpoint,
         }
     }
 
-    pub async fn notify_paper(&self) -> Result<()> {
-        let papers = self.arxiv_client.search_past_5_to_6_days().await?;
+    // dates は再送のために指定する投稿日の期間 (両端を含む)
+    pub async fn notify_paper(&self, dates: Option<(NaiveDate, NaiveDate)>) -> Result<()> {
+        let window = match dates {
+            Some((from, to)) => PaperWindow::from_dates(&DateRange::new(from, to)?),
+            None => self.paper_window(Utc::now())?,
+        };
+        println!("search papers submitted {} - {}", window.from, window.to);
+
+        let mut candidates = vec![];
+        for paper in self.arxiv_client.search(&window).await? {
+            if self.seen_paper_store.contains(&paper.arxiv_id())? {
+                println!("skip already posted paper: {}", paper.url);
+                continue;
+            }
+            candidates.push(paper);
+        }
+        let papers = self.rank_papers(candidates).await;
         if papers.is_empty() {
             println!("not found paper");
-            return Ok(());
+            return self.save_checkpoint(dates.is_none(), &window);
         }
 
+        // 要約は並列に行い、投稿は検索結果の順に行う
+        let engine = self.config.paper_engine;
         let handles = papers
             .into_iter()
             .map(|p| {
                 let openai = Arc::clone(&self.openai_client);
-                let slack = Arc::clone(&self.slack_client);
                 task::spawn(async move {
-                    // println!("start spawn");
-
-                    let engine = model::openai::Engine::Gpt4;
                     let paper_summary = openai.summarize_paper(&p, &engine).await?;
-                    slack.post_message(&p, &paper_summary, &engine).await?;
-                    // println!("end spawn");
-                    Ok::<(), anyhow::Error>(())
+                    Ok::<_, anyhow::Error>((p, paper_summary))
                 })
             })
             .collect::<Vec<_>>();
 
-        join_all(handles)
+        // 要約に失敗した論文があっても、他の論文は投稿してからエラーを返す
+        let (summarized, failed): (Vec<_>, Vec<_>) = join_all(handles)
             .await
             .into_iter()
             .map(|res| res.map_err(anyhow::Error::from).and_then(|x| x))
-            .collect::<Result<Vec<_>>>()?;
+            .partition(Result::is_ok);
+        let summarized: Vec<_> = summarized.into_iter().filter_map(Result::ok).collect();
+
+        if !summarized.is_empty() {
+            self.post_papers(&summarized, &engine).await?;
+        }
+        if let Some(Err(e)) = failed.into_iter().next() {
+            return Err(e);
+        }
+
+        self.save_checkpoint(dates.is_none(), &window)
+    }
+
+    // ARXIV_SINCE_LAST_RUN の場合は前回の実行で検索した期間の続きから
+    fn paper_window(&self, now: DateTime<Utc>) -> Result<PaperWindow> {
+        let config = &self.config.arxiv_window;
+        let to = now - Duration::days(config.offset_days);
+        let checkpoint = if config.since_last_run {
+            self.checkpoint.load()?
+        } else {
+            None
+        };
+        // ARXIV_OFFSET_DAYS を増やした場合などは、前回の続きが to より後になる
+        let from = checkpoint
+            .map(|checkpoint| checkpoint.min(to))
+            .unwrap_or(to - Duration::days(config.width_days));
+        PaperWindow::new(from, to)
+    }
+
+    // 期間を指定した再送では、チェックポイントを進めない
+    fn save_checkpoint(&self, scheduled: bool, window: &PaperWindow) -> Result<()> {
+        if scheduled && self.config.arxiv_window.since_last_run {
+            self.checkpoint.save(window.to)?;
+        }
+        Ok(())
+    }
+
+    async fn post_papers(
+        &self,
+        papers: &[(PaperModel, PaperSummaryModel)],
+        engine: &Engine,
+    ) -> Result<()> {
+        match self.config.paper_digest {
+            PaperDigest::Separate => {
+                for (paper, paper_summary) in papers {
+                    self.slack_client
+                        .post_message(paper, paper_summary, engine, None)
+                        .await?;
+                    self.seen_paper_store.insert(&paper.arxiv_id())?;
+                }
+            }
+            PaperDigest::Thread => {
+                let parent = self
+                    .slack_client
+                    .post_paper_digest_parent(papers.len())
+                    .await?;
+                for (paper, paper_summary) in papers {
+                    self.slack_client
+                        .post_message(paper, paper_summary, engine, Some(&parent.ts))
+                        .await?;
+                    self.seen_paper_store.insert(&paper.arxiv_id())?;
+                }
+            }
+            PaperDigest::Combined => {
+                self.slack_client.post_paper_digest(papers, engine).await?;
+                for (paper, _) in papers {
+                    self.seen_paper_store.insert(&paper.arxiv_id())?;
+                }
+            }
+        }
 
         Ok(())
     }
+
+    // 点数の高い順に PAPER_COUNT 件を選ぶ (同点の場合は検索結果の順)
+    async fn rank_papers(&self, papers: Vec<PaperModel>) -> Vec<PaperModel> {
+        let ranking = &self.config.paper_ranking;
+        let mut scored: Vec<_> = papers
+            .into_iter()
+            .map(|paper| (match_score(&paper, ranking), paper))
+            .collect();
+        sort_by_score(&mut scored);
+
+        if ranking.llm {
+            scored.truncate(self.config.paper_count * LLM_CANDIDATES_PER_PAPER);
+            let interests = if ranking.keywords.is_empty() {
+                self.config
+                    .arxiv_search_query()
+                    .unwrap_or(self.config.arxiv_query.clone())
+            } else {
+                ranking.keywords.join(", ")
+            };
+            let llm_scores = join_all(scored.iter().map(|(_, paper)| {
+                self.openai_client
+                    .rate_relevance(paper, &interests, &Engine::Gpt3_5Turbo)
+            }))
+            .await;
+            for ((score, paper), llm_score) in scored.iter_mut().zip(llm_scores) {
+                match llm_score {
+                    Ok(llm_score) => *score += llm_score,
+                    // 採点に失敗した論文はキーワードと著者の点数だけで並べる
+                    Err(e) => println!("failed to rate paper {}: {}", paper.url, e),
+                }
+            }
+            sort_by_score(&mut scored);
+        }
+
+        scored
+            .into_iter()
+            .take(self.config.paper_count)
+            .map(|(_, paper)| paper)
+            .collect()
+    }
+}
+
+// キーワードはタイトルに含まれる方を高くする
+fn match_score(paper: &PaperModel, ranking: &PaperRankingConfig) -> f64 {
+    let title = paper.title.to_lowercase();
+    let summary = paper.summary.to_lowercase();
+    let keyword_score: f64 = ranking
+        .keywords
+        .iter()
+        .map(|keyword| {
+            let keyword = keyword.to_lowercase();
+            if title.contains(&keyword) {
+                TITLE_KEYWORD_SCORE
+            } else if summary.contains(&keyword) {
+                SUMMARY_KEYWORD_SCORE
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    let author_score = if paper.authors.iter().any(|author| {
+        ranking
+            .authors
+            .iter()
+            .any(|allowed| author.eq_ignore_ascii_case(allowed))
+    }) {
+        AUTHOR_SCORE
+    } else {
+        0.0
+    };
+    keyword_score + author_score
+}
+
+fn sort_by_score(scored: &mut [(f64, PaperModel)]) {
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
 }